@@ -2,6 +2,18 @@ use rodio::{source::Source, Decoder};
 use rodio::{OutputStreamHandle, Sink};
 use std::fs::File;
 use std::io::BufReader;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// volume from the settings as f32 bits, starts at 1
+static VOLUME: AtomicU32 = AtomicU32::new(0x3f80_0000);
+
+pub fn set_volume(volume: f64) {
+    VOLUME.store((volume as f32).to_bits(), Ordering::Relaxed);
+}
+
+pub fn volume() -> f32 {
+    f32::from_bits(VOLUME.load(Ordering::Relaxed))
+}
 
 pub fn play_audio(stream_handle: &OutputStreamHandle, path: &str) {
     // Load a sound from a file, using a path relative to Cargo.toml
@@ -10,7 +22,7 @@ pub fn play_audio(stream_handle: &OutputStreamHandle, path: &str) {
     let source = Decoder::new(file).unwrap();
     // Play the sound directly on the device
     stream_handle
-        .play_raw(source.convert_samples())
+        .play_raw(source.convert_samples().amplify(volume()))
         .expect("couldn't play sound");
 }
 
@@ -18,6 +30,7 @@ pub fn create_infinite_sink(level_audio_handle: &OutputStreamHandle, path: &str)
     let file = BufReader::new(File::open(path).unwrap());
     let source = Decoder::new(file).unwrap();
     let sink = Sink::try_new(level_audio_handle).unwrap();
+    sink.set_volume(volume());
 
     let source = source.repeat_infinite();
     sink.append(source);
@@ -32,7 +45,7 @@ pub fn audio_loop(stream_handle: &OutputStreamHandle, path: &str) {
     let source = Decoder::new(file).unwrap();
     let source = source.repeat_infinite();
     // Play the sound directly on the device
-    let _ = stream_handle.play_raw(source.convert_samples());
+    let _ = stream_handle.play_raw(source.convert_samples().amplify(volume()));
 }
 
 // #[cfg(test)]
@@ -0,0 +1,116 @@
+use std::f64::consts::TAU;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::camera::Camera;
+use crate::color::Palette;
+use crate::light::Light;
+use crate::loader;
+use crate::mat::Vec3;
+use crate::output::{self, Memory, Sink};
+use crate::renderer::{Backend, OutputMode, Screen};
+
+// the biggest screen the game renders to, see `renderer::get_terminal_size`
+const SCREEN: Screen = Screen::with_size(220, 64, OutputMode::Color, Palette::TRUE_COLOR);
+const DEFAULT_FRAMES: usize = 200;
+
+/// `termtrack bench <level_file> [frames]`, renders the same views of a level with every
+/// renderer and prints the time per frame of each. Returns the exit code.
+pub fn run(args: &[String]) -> i32 {
+    let (Some(path), frames) = (args.first(), args.get(1)) else {
+        eprintln!("usage: termtrack bench <level_file> [frames]");
+        return 2;
+    };
+    let frames = match frames.map(|n| n.parse::<usize>()) {
+        None => DEFAULT_FRAMES,
+        Some(Ok(n)) if n > 0 => n,
+        Some(_) => {
+            eprintln!("frames must be a positive number");
+            return 2;
+        }
+    };
+    let map = match loader::load(&PathBuf::from(path)) {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("{path}: {e}");
+            return 1;
+        }
+    };
+
+    // a full turn at the start, looking a little up and down on the way
+    let views: Vec<Camera> = (0..frames)
+        .map(|i| {
+            let turn = i as f64 / frames as f64;
+            Camera {
+                pos: Vec3 {
+                    x: map.start_pos.0,
+                    y: map.start_pos.1,
+                    z: map.start_pos.2,
+                },
+                rotation: Vec3 {
+                    x: turn * TAU,
+                    y: (turn * TAU * 3.).sin() * 0.4,
+                    z: 0.,
+                },
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    println!(
+        "{} frames of {}x{} on {} ({} triangles)",
+        frames,
+        SCREEN.w,
+        SCREEN.h,
+        map.level_name,
+        map.bvh.tris().len()
+    );
+    for (backend, shadows) in Backend::ALL
+        .into_iter()
+        .flat_map(|backend| [(backend, false), (backend, true)])
+    {
+        let renderer = backend.renderer();
+        let light = Light {
+            shadows,
+            ..map.light
+        };
+        let mut total = Duration::ZERO;
+        for camera in &views {
+            let start = Instant::now();
+            let buffer = renderer.render(&SCREEN, camera, &map.bvh, &[], &light);
+            total += start.elapsed();
+            std::hint::black_box(buffer);
+        }
+        let per_frame = total.as_secs_f64() / frames as f64;
+        let name = if shadows {
+            format!("{} + shadows", backend.name())
+        } else {
+            backend.name().to_string()
+        };
+        println!(
+            "{:>18}: {:7.2} ms/frame ({:.0} fps)",
+            name,
+            per_frame * 1000.,
+            1. / per_frame
+        );
+    }
+
+    // what the frames cost to send, only the changes after the first
+    let renderer = Backend::Raster.renderer();
+    for mode in OutputMode::ALL {
+        let mut screen = Screen::with_size(SCREEN.w, SCREEN.h, mode, Palette::TRUE_COLOR);
+        let memory = Memory::default();
+        let previous = output::set_sink(Sink::Memory(memory.clone()));
+        for camera in &views {
+            let buffer = renderer.render(&screen, camera, &map.bvh, &[], &map.light);
+            screen.draw(&buffer, "");
+        }
+        output::set_sink(previous);
+        println!(
+            "{:>18}: {:7.1} KiB/frame",
+            mode.name(),
+            memory.contents().len() as f64 / 1024. / frames as f64
+        );
+    }
+    0
+}
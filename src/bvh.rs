@@ -0,0 +1,306 @@
+use crate::mat::{Tri, Vec3};
+
+// triangles in a leaf, smaller is faster to trace but deeper to walk
+const MAX_LEAF: usize = 4;
+// enough for a tree over 2^64 triangles split at the median
+const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Copy, Debug)]
+struct Aabb {
+    min: Vec3,
+    max: Vec3,
+}
+
+impl Aabb {
+    fn empty() -> Self {
+        Aabb {
+            min: Vec3 {
+                x: f64::MAX,
+                y: f64::MAX,
+                z: f64::MAX,
+            },
+            max: Vec3 {
+                x: f64::MIN,
+                y: f64::MIN,
+                z: f64::MIN,
+            },
+        }
+    }
+
+    fn grow(&mut self, p: Vec3) {
+        self.min = Vec3 {
+            x: self.min.x.min(p.x),
+            y: self.min.y.min(p.y),
+            z: self.min.z.min(p.z),
+        };
+        self.max = Vec3 {
+            x: self.max.x.max(p.x),
+            y: self.max.y.max(p.y),
+            z: self.max.z.max(p.z),
+        };
+    }
+
+    // distance along the ray where it enters the box, if it does before `max_t`
+    fn hit(&self, ro: Vec3, inv_rd: Vec3, max_t: f64) -> Option<f64> {
+        let (mut near, mut far) = (0., max_t);
+        for (o, inv, min, max) in [
+            (ro.x, inv_rd.x, self.min.x, self.max.x),
+            (ro.y, inv_rd.y, self.min.y, self.max.y),
+            (ro.z, inv_rd.z, self.min.z, self.max.z),
+        ] {
+            let t0 = (min - o) * inv;
+            let t1 = (max - o) * inv;
+            near = t0.min(t1).max(near);
+            far = t0.max(t1).min(far);
+        }
+        (near <= far).then_some(near)
+    }
+}
+
+fn centroid(tri: &Tri) -> Vec3 {
+    (tri.v0 + tri.v1 + tri.v2) / 3.
+}
+
+// a leaf holds tris[first..first + count], an inner node has its children at first and first + 1
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    bounds: Aabb,
+    first: usize,
+    count: usize,
+}
+
+/// Nearest opaque and nearest translucent triangle along a ray, distances in ray lengths
+#[derive(Clone, Copy, Debug)]
+pub struct RayHit {
+    pub opaque: Option<Tri>,
+    pub opaque_dist: f64,
+    pub translucent: Option<Tri>,
+    pub translucent_dist: f64,
+}
+
+impl RayHit {
+    /// No hit yet, nothing further than `max_dist` will count
+    pub fn new(max_dist: f64) -> Self {
+        RayHit {
+            opaque: None,
+            opaque_dist: max_dist,
+            translucent: None,
+            translucent_dist: max_dist,
+        }
+    }
+
+    pub fn test(&mut self, tri: &Tri, ro: Vec3, rd: Vec3) {
+        let (hit, distance) = tri.hit_mt(ro, rd);
+        if hit {
+            self.add(tri, distance);
+        }
+    }
+
+    /// Keeps `tri` if it's closer than what was hit so far
+    pub fn add(&mut self, tri: &Tri, distance: f64) {
+        if tri.alpha < 1. {
+            if distance < self.translucent_dist {
+                self.translucent_dist = distance;
+                self.translucent = Some(*tri);
+            }
+        } else if distance < self.opaque_dist {
+            self.opaque_dist = distance;
+            self.opaque = Some(*tri);
+        }
+    }
+}
+
+/// Bounding volume hierarchy over the triangles of a level that never move.
+/// Built once when the level is loaded, split at the median along the longest axis.
+#[derive(Debug)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    tris: Vec<Tri>,
+}
+
+impl Bvh {
+    pub fn new(tris: Vec<Tri>) -> Self {
+        let mut bvh = Bvh {
+            nodes: vec![Node {
+                bounds: Aabb::empty(),
+                first: 0,
+                count: tris.len(),
+            }],
+            tris,
+        };
+        bvh.split(0, 0);
+        bvh
+    }
+
+    fn split(&mut self, node: usize, depth: usize) {
+        let Node { first, count, .. } = self.nodes[node];
+        let mut bounds = Aabb::empty();
+        let mut centroids = Aabb::empty();
+        for tri in &self.tris[first..first + count] {
+            for v in [tri.v0, tri.v1, tri.v2] {
+                bounds.grow(v);
+            }
+            centroids.grow(centroid(tri));
+        }
+        self.nodes[node].bounds = bounds;
+        if count <= MAX_LEAF || depth + 1 >= MAX_DEPTH {
+            return;
+        }
+
+        let size = centroids.max - centroids.min;
+        let axis = |v: Vec3| {
+            if size.x >= size.y && size.x >= size.z {
+                v.x
+            } else if size.y >= size.z {
+                v.y
+            } else {
+                v.z
+            }
+        };
+        let half = count / 2;
+        self.tris[first..first + count]
+            .select_nth_unstable_by(half, |a, b| axis(centroid(a)).total_cmp(&axis(centroid(b))));
+
+        let left = self.nodes.len();
+        self.nodes.push(Node {
+            bounds: Aabb::empty(),
+            first,
+            count: half,
+        });
+        self.nodes.push(Node {
+            bounds: Aabb::empty(),
+            first: first + half,
+            count: count - half,
+        });
+        self.nodes[node] = Node {
+            bounds,
+            first: left,
+            count: 0,
+        };
+        self.split(left, depth + 1);
+        self.split(left + 1, depth + 1);
+    }
+
+    pub fn tris(&self) -> &[Tri] {
+        &self.tris
+    }
+
+    /// Adds the triangles along the ray to `hit`, skipping boxes behind its opaque hit
+    pub fn intersect(&self, ro: Vec3, rd: Vec3, hit: &mut RayHit) {
+        if self.tris.is_empty() {
+            return;
+        }
+        let inv_rd = Vec3 {
+            x: 1. / rd.x,
+            y: 1. / rd.y,
+            z: 1. / rd.z,
+        };
+        let mut stack = [0; MAX_DEPTH];
+        let mut len = 0;
+        if self.nodes[0]
+            .bounds
+            .hit(ro, inv_rd, hit.opaque_dist)
+            .is_some()
+        {
+            len = 1;
+        }
+        while len > 0 {
+            len -= 1;
+            let node = self.nodes[stack[len]];
+            if node.count > 0 {
+                for tri in &self.tris[node.first..node.first + node.count] {
+                    hit.test(tri, ro, rd);
+                }
+                continue;
+            }
+            let near = self.nodes[node.first]
+                .bounds
+                .hit(ro, inv_rd, hit.opaque_dist);
+            let far = self.nodes[node.first + 1]
+                .bounds
+                .hit(ro, inv_rd, hit.opaque_dist);
+            // the closer child goes on top so its hits can cut the other one short
+            let (first, second) = match (near, far) {
+                (Some(a), Some(b)) if b < a => (Some(node.first), Some(node.first + 1)),
+                (a, b) => (b.map(|_| node.first + 1), a.map(|_| node.first)),
+            };
+            for child in [first, second].into_iter().flatten() {
+                stack[len] = child;
+                len += 1;
+            }
+        }
+    }
+
+    /// Whether any opaque triangle is on the ray closer than `max_dist`, stops at the
+    /// first one found rather than looking for the nearest
+    pub fn occluded(&self, ro: Vec3, rd: Vec3, max_dist: f64) -> bool {
+        if self.tris.is_empty() {
+            return false;
+        }
+        let inv_rd = Vec3 {
+            x: 1. / rd.x,
+            y: 1. / rd.y,
+            z: 1. / rd.z,
+        };
+        let mut stack = [0; MAX_DEPTH];
+        let mut len = 1;
+        while len > 0 {
+            len -= 1;
+            let node = self.nodes[stack[len]];
+            if node.bounds.hit(ro, inv_rd, max_dist).is_none() {
+                continue;
+            }
+            if node.count > 0 {
+                let blocked = self.tris[node.first..node.first + node.count]
+                    .iter()
+                    .filter(|tri| tri.alpha >= 1.)
+                    .any(|tri| {
+                        let (hit, distance) = tri.hit_mt(ro, rd);
+                        hit && distance < max_dist
+                    });
+                if blocked {
+                    return true;
+                }
+                continue;
+            }
+            stack[len] = node.first;
+            stack[len + 1] = node.first + 1;
+            len += 2;
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn finds_the_same_hits_as_testing_every_tri() {
+        let map = crate::loader::load(&PathBuf::from("level_pack_0/level_1.txt")).unwrap();
+        let bvh = &map.bvh;
+        let tris = &bvh.tris;
+        let ro = Vec3 {
+            x: map.start_pos.0,
+            y: map.start_pos.1 - 3.,
+            z: map.start_pos.2,
+        };
+        for i in 0..200 {
+            let angle = i as f64 * 0.31;
+            let rd = Vec3 {
+                x: angle.cos(),
+                y: (i as f64 * 0.17).sin() * 0.8,
+                z: angle.sin(),
+            };
+            let mut all = RayHit::new(f64::MAX);
+            for tri in tris {
+                all.test(tri, ro, rd);
+            }
+            let mut fast = RayHit::new(f64::MAX);
+            bvh.intersect(ro, rd, &mut fast);
+            assert_eq!(fast.opaque_dist, all.opaque_dist, "ray {i}");
+            assert_eq!(bvh.occluded(ro, rd, 20.), all.opaque_dist < 20., "ray {i}");
+        }
+    }
+}
@@ -4,11 +4,43 @@ use crate::mat::Vec3;
 pub struct Camera {
     pub pos: Vec3,
     pub focus_length: f64,
+    // nothing further than this is drawn, it is all fog by then
+    pub view_dist: f64,
     pub rotation: Vec3,
     pub vel: Vec3,
 }
 
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            pos: Vec3 {
+                x: 0.,
+                y: 0.,
+                z: 0.,
+            },
+            focus_length: 1.5, //2
+            view_dist: 45.,
+            rotation: Vec3 {
+                x: 1.75,
+                y: 0.0,
+                z: 0.0,
+            },
+            vel: Vec3 {
+                x: 0.,
+                y: 0.,
+                z: 0.,
+            },
+        }
+    }
+}
+
 impl Camera {
+    /// The view distance in the unit of the renderers, lengths of the ray through a pixel.
+    /// That ray is `focus_length` long in the middle of the screen.
+    pub fn max_ray_dist(&self) -> f64 {
+        self.view_dist / self.focus_length
+    }
+
     pub fn update_pos(&mut self, dt: f64) {
         // check collision
 
@@ -0,0 +1,88 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde_json::json;
+
+/// Writes what the game draws to an asciicast v2 file, which `asciinema play` and the
+/// asciinema web player show like a video. A header line and then one line per write.
+pub struct Recorder {
+    out: Box<dyn Write + Send>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path, width: usize, height: usize, title: &str) -> Result<Self, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Recorder::new(Box::new(BufWriter::new(file)), width, height, title)
+            .map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    pub fn new(
+        mut out: Box<dyn Write + Send>,
+        width: usize,
+        height: usize,
+        title: &str,
+    ) -> Result<Self, String> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        let header = json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": timestamp,
+            "title": title,
+            "env": {"TERM": std::env::var("TERM").unwrap_or_default()},
+        });
+        writeln!(out, "{header}").map_err(|e| e.to_string())?;
+        Ok(Recorder {
+            out,
+            start: Instant::now(),
+        })
+    }
+
+    /// Adds `data` as output at the current time since the recording started
+    pub fn write(&mut self, data: &[u8]) {
+        // microseconds are as fine as players go
+        let time = (self.start.elapsed().as_secs_f64() * 1e6).round() / 1e6;
+        let event = json!([time, "o", String::from_utf8_lossy(data)]);
+        // a full disk ends the recording, not the run
+        let _ = writeln!(self.out, "{event}");
+    }
+
+    pub fn flush(&mut self) {
+        let _ = self.out.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{Memory, Sink};
+    use serde_json::Value;
+
+    #[test]
+    fn writes_header_and_timed_events() {
+        let memory = Memory::default();
+        let mut recorder =
+            Recorder::new(Box::new(Sink::Memory(memory.clone())), 80, 24, "level_0").unwrap();
+        recorder.write(b"\x1b[1;1Hhello");
+        recorder.write("\u{2580}".as_bytes());
+
+        let contents = memory.contents();
+        let lines: Vec<Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines[0]["width"], 80);
+        assert_eq!(lines[0]["height"], 24);
+        assert_eq!(lines[1][1], "o");
+        assert_eq!(lines[1][2], "\x1b[1;1Hhello");
+        assert_eq!(lines[2][2], "\u{2580}");
+        assert!(lines[2][0].as_f64().unwrap() >= lines[1][0].as_f64().unwrap());
+    }
+}
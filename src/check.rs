@@ -0,0 +1,355 @@
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::loader::{self, MapPos, GH};
+use crate::pack;
+
+// player height and jump height in floors, from the collider and jump speed in game.rs
+const PLAYER_HEIGHT: f64 = 5.5 / GH;
+const JUMP_HEIGHT: f64 = 0.7;
+// holes a running jump clears, diagonally it goes about 3.5 tiles
+const MAX_GAP: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Something wrong with a level, errors stop it from being played
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    pub severity: Severity,
+    pub pos: Option<MapPos>,
+    pub message: String,
+}
+
+impl Problem {
+    fn error(pos: Option<MapPos>, message: String) -> Self {
+        Problem {
+            severity: Severity::Error,
+            pos,
+            message,
+        }
+    }
+
+    fn warning(pos: Option<MapPos>, message: String) -> Self {
+        Problem {
+            severity: Severity::Warning,
+            pos,
+            message,
+        }
+    }
+}
+
+// a solid piece of a grid cell, heights in floors from the bottom of the level
+#[derive(Clone, Copy)]
+struct Block {
+    bottom: f64,
+    top: f64,
+    deadly: bool,
+}
+
+// the map as characters, indexed [floor][row][column]
+struct Grid {
+    floors: Vec<Vec<Vec<char>>>,
+}
+
+// standing on top of a block at a grid cell
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Spot {
+    row: usize,
+    col: usize,
+    // index of the block in the column
+    block: usize,
+}
+
+impl Grid {
+    fn column(&self, row: usize, col: usize) -> Vec<Block> {
+        let mut blocks = vec![];
+        for (floor, rows) in self.floors.iter().enumerate() {
+            let base = floor as f64;
+            let (height, deadly) = match rows.get(row).and_then(|r| r.get(col)) {
+                Some('.' | 'e' | 'E') => (0.1, false),
+                Some('S') => (0.15, false),
+                Some('v') => (0.2, true),
+                Some('x') => (0.5, false),
+                Some('X') => (1., false),
+                _ => continue,
+            };
+            blocks.push(Block {
+                bottom: base,
+                top: base + height,
+                deadly,
+            });
+        }
+        blocks
+    }
+
+    // nothing solid between the two heights
+    fn clear(&self, row: usize, col: usize, from: f64, to: f64) -> bool {
+        self.column(row, col)
+            .iter()
+            .all(|b| b.top <= from + 0.01 || b.bottom >= to - 0.01)
+    }
+
+    fn standable(&self, spot: Spot) -> Option<f64> {
+        let block = *self.column(spot.row, spot.col).get(spot.block)?;
+        (!block.deadly && self.clear(spot.row, spot.col, block.top, block.top + PLAYER_HEIGHT))
+            .then_some(block.top)
+    }
+
+    fn spots(&self, row: usize, col: usize) -> Vec<(Spot, f64)> {
+        (0..self.column(row, col).len())
+            .map(|block| Spot { row, col, block })
+            .filter_map(|spot| Some((spot, self.standable(spot)?)))
+            .collect()
+    }
+
+    // where a walk, step or jump from `spot` can land
+    fn moves(&self, spot: Spot, height: f64) -> Vec<Spot> {
+        let mut res = vec![];
+        for (dr, dc) in [(0, 1), (1, 0), (0, -1), (-1, 0)] {
+            for dist in 1..=MAX_GAP + 1 {
+                let (Some(row), Some(col)) = (
+                    spot.row.checked_add_signed(dr * dist as isize),
+                    spot.col.checked_add_signed(dc * dist as isize),
+                ) else {
+                    break;
+                };
+                // stepping up is only possible right next to a block, jumps over holes land lower
+                let reach = if dist == 1 { JUMP_HEIGHT } else { 0.05 };
+                for (next, top) in self.spots(row, col) {
+                    let peak = height.max(top) + PLAYER_HEIGHT;
+                    if top <= height + reach
+                        && self.clear(spot.row, spot.col, height, peak)
+                        && self.clear(row, col, top, peak)
+                    {
+                        res.push(next);
+                    }
+                }
+                // the jump needs room above the cell it passes over
+                if !self.clear(row, col, height + 0.1, height + JUMP_HEIGHT + PLAYER_HEIGHT) {
+                    break;
+                }
+            }
+        }
+        res
+    }
+}
+
+/// Checks a level for everything the loader refuses and for mistakes that still load
+pub fn lint(map_string: &str) -> Vec<Problem> {
+    let mut problems = vec![];
+    let mut grid = Grid { floors: vec![] };
+    let mut starts = vec![];
+    let mut ends = vec![];
+    for (floor, (first_line, text)) in loader::separate_map(map_string).iter().enumerate() {
+        let lines: Vec<&str> = text.lines().map(|l| l.trim_end_matches('\r')).collect();
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let mut rows = vec![];
+        for (z, line) in lines.iter().enumerate() {
+            let pos = |x: usize| MapPos {
+                floor: floor + 1,
+                line: first_line + z + 1,
+                col: x + 1,
+            };
+            let len = line.chars().count();
+            if len != 0 && len != width {
+                problems.push(Problem::warning(
+                    Some(pos(len)),
+                    format!("row is {len} wide, the widest row on this floor is {width}"),
+                ));
+            }
+            let row: Vec<char> = line.trim_end().chars().collect();
+            for (x, c) in row.iter().enumerate() {
+                match c {
+                    'S' => starts.push((pos(x), z, x)),
+                    'E' => ends.push((z, x)),
+                    'e' => {
+                        let beside = [(0, 1), (1, 0), (0, -1), (-1, 0)].iter().any(|(dz, dx)| {
+                            let neighbour = z
+                                .checked_add_signed(*dz)
+                                .and_then(|nz| lines.get(nz))
+                                .zip(x.checked_add_signed(*dx))
+                                .and_then(|(l, nx)| l.chars().nth(nx));
+                            matches!(neighbour, Some('.' | 'e' | 'S' | 'E' | 'x' | 'v'))
+                        });
+                        if !beside {
+                            problems.push(Problem::warning(
+                                Some(pos(x)),
+                                "enemy has no floor next to it".to_string(),
+                            ));
+                        }
+                    }
+                    'X' | 'x' | 'v' | '.' | ' ' => (),
+                    c => problems.push(Problem::error(
+                        Some(pos(x)),
+                        format!("invalid character '{c}'"),
+                    )),
+                }
+            }
+            rows.push(row);
+        }
+        grid.floors.push(rows);
+    }
+
+    match starts.as_slice() {
+        [] => problems.push(Problem::error(None, "level has no start (S)".to_string())),
+        [_] => (),
+        [_, more @ ..] => {
+            for (pos, ..) in more {
+                problems.push(Problem::error(
+                    Some(*pos),
+                    "level has more than one start (S)".to_string(),
+                ))
+            }
+        }
+    }
+    if ends.is_empty() {
+        problems.push(Problem::error(None, "level has no end (E)".to_string()));
+    }
+
+    if let (Some((pos, row, col)), false) = (starts.first(), ends.is_empty()) {
+        if !end_reachable(&grid, *row, *col, &ends) {
+            problems.push(Problem::error(
+                Some(*pos),
+                "no end (E) can be reached from the start".to_string(),
+            ));
+        }
+    }
+    problems
+}
+
+// flood fill over everything the player can stand on, going up through stairs and down holes
+fn end_reachable(grid: &Grid, row: usize, col: usize, ends: &[(usize, usize)]) -> bool {
+    let mut seen = HashSet::new();
+    let mut queue: VecDeque<Spot> = grid
+        .spots(row, col)
+        .into_iter()
+        .map(|(spot, _)| spot)
+        .collect();
+    while let Some(spot) = queue.pop_front() {
+        if !seen.insert(spot) {
+            continue;
+        }
+        if ends.contains(&(spot.row, spot.col)) {
+            return true;
+        }
+        let Some(height) = grid.standable(spot) else {
+            continue;
+        };
+        queue.extend(grid.moves(spot, height));
+    }
+    false
+}
+
+/// Level files to check, a folder means every level of its pack
+fn level_files(path: &Path) -> Result<Vec<PathBuf>, String> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let pack = pack::load(path).map_err(|e| format!("{}: error: {e}", path.display()))?;
+    Ok(pack.levels.into_iter().map(|level| level.path).collect())
+}
+
+/// `termtrack check <path>...`, prints the problems of every level and returns the exit code
+pub fn run(paths: &[String]) -> i32 {
+    if paths.is_empty() {
+        eprintln!("usage: termtrack check <level_file_or_folder>...");
+        return 2;
+    }
+    let (mut checked, mut errors, mut warnings) = (0, 0, 0);
+    for path in paths {
+        let files = match level_files(Path::new(path)) {
+            Ok(files) => files,
+            Err(e) => {
+                println!("{e}");
+                errors += 1;
+                continue;
+            }
+        };
+        for file in files {
+            checked += 1;
+            let problems = match fs::read_to_string(&file) {
+                Ok(map) => {
+                    let mut problems = lint(&map);
+                    // anything the loader still refuses that the lint missed
+                    if problems.iter().all(|p| p.severity != Severity::Error) {
+                        if let Err(e) = loader::parse(String::new(), map) {
+                            problems.push(Problem::error(None, e.to_string()));
+                        }
+                    }
+                    problems
+                }
+                Err(e) => vec![Problem::error(None, format!("couldn't read level: {e}"))],
+            };
+            for problem in problems {
+                let location = match problem.pos {
+                    Some(pos) => format!("{}:{}:{}", file.display(), pos.line, pos.col),
+                    None => file.display().to_string(),
+                };
+                let severity = match problem.severity {
+                    Severity::Error => {
+                        errors += 1;
+                        "error"
+                    }
+                    Severity::Warning => {
+                        warnings += 1;
+                        "warning"
+                    }
+                };
+                let floor = problem
+                    .pos
+                    .map(|pos| format!(" (floor {})", pos.floor))
+                    .unwrap_or_default();
+                println!("{location}: {severity}: {}{floor}", problem.message);
+            }
+        }
+    }
+    println!("checked {checked} levels: {errors} errors, {warnings} warnings");
+    (errors > 0) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(map: &str) -> Vec<String> {
+        lint(map)
+            .into_iter()
+            .filter(|p| p.severity == Severity::Error)
+            .map(|p| p.message)
+            .collect()
+    }
+
+    #[test]
+    fn bundled_levels_pass() {
+        for file in level_files(Path::new("level_pack_0")).unwrap() {
+            let map = fs::read_to_string(&file).unwrap();
+            assert_eq!(errors(&map), Vec::<String>::new(), "{}", file.display());
+        }
+    }
+
+    #[test]
+    fn finds_unreachable_end() {
+        // five holes is too far to jump
+        assert_eq!(
+            errors("S.     .E"),
+            vec!["no end (E) can be reached from the start"]
+        );
+        assert!(errors("S.   .E").is_empty());
+        // the upper floor needs a stair
+        assert_eq!(errors("S..\nsep\n   .E").len(), 1);
+        assert!(errors("S.x\nsep\n   .E").is_empty());
+    }
+
+    #[test]
+    fn warns_about_ragged_rows() {
+        let problems = lint("XXXX\nXSE\nXXXX");
+        assert_eq!(problems.len(), 1);
+        assert_eq!(problems[0].severity, Severity::Warning);
+        assert_eq!(problems[0].pos.map(|p| (p.line, p.col)), Some((2, 4)));
+    }
+}
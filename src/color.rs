@@ -0,0 +1,234 @@
+use std::env;
+
+use crate::mat::Vec3;
+
+/// How many colours the terminal can show
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorDepth {
+    TrueColor,
+    // xterm 256 colour palette, a 6x6x6 cube and a grey ramp
+    Ansi256,
+    // the 16 standard colours, their exact shade is up to the terminal theme
+    Ansi16,
+}
+
+/// Picks the colour depth by name ("truecolor", "256" or "16"), without one it is guessed
+/// from `COLORTERM`, `TERM` and `WT_SESSION`
+pub fn depth(kind: Option<&str>) -> Result<ColorDepth, String> {
+    match kind {
+        Some("truecolor" | "24bit") => Ok(ColorDepth::TrueColor),
+        Some("256") => Ok(ColorDepth::Ansi256),
+        Some("16") => Ok(ColorDepth::Ansi16),
+        Some("auto") | None => Ok(detect(
+            &env::var("COLORTERM").unwrap_or_default(),
+            &env::var("TERM").unwrap_or_default(),
+            env::var_os("WT_SESSION").is_some(),
+        )),
+        Some(kind) => Err(format!("unknown colors '{kind}', use truecolor, 256 or 16")),
+    }
+}
+
+fn detect(colorterm: &str, term: &str, wt_session: bool) -> ColorDepth {
+    // Windows Terminal doesn't set either, but has always had true colour
+    if colorterm.contains("truecolor")
+        || colorterm.contains("24bit")
+        || term.contains("direct")
+        || wt_session
+        || (cfg!(windows) && term.is_empty())
+    {
+        ColorDepth::TrueColor
+    } else if term.contains("256") {
+        ColorDepth::Ansi256
+    } else {
+        ColorDepth::Ansi16
+    }
+}
+
+// levels of each channel in the 256 colour cube
+const CUBE: [f64; 6] = [0., 95., 135., 175., 215., 255.];
+
+// the xterm defaults, close to what most themes use
+const ANSI16: [(f64, f64, f64); 16] = [
+    (0., 0., 0.),
+    (205., 0., 0.),
+    (0., 205., 0.),
+    (205., 205., 0.),
+    (0., 0., 238.),
+    (205., 0., 205.),
+    (0., 205., 205.),
+    (229., 229., 229.),
+    (127., 127., 127.),
+    (255., 0., 0.),
+    (0., 255., 0.),
+    (255., 255., 0.),
+    (92., 92., 255.),
+    (255., 0., 255.),
+    (0., 255., 255.),
+    (255., 255., 255.),
+];
+
+// 4x4 ordered dither thresholds
+const BAYER: [[f64; 4]; 4] = [
+    [0., 8., 2., 10.],
+    [12., 4., 14., 6.],
+    [3., 11., 1., 9.],
+    [15., 7., 13., 5.],
+];
+
+/// A colour as the terminal is told it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Paint {
+    Rgb(u8, u8, u8),
+    // index into the 256 colour palette, the first 16 are the standard colours
+    Indexed(u8),
+}
+
+impl Paint {
+    /// Select graphic rendition parameters setting this as the foreground or background
+    pub fn sgr(self, foreground: bool) -> String {
+        match self {
+            Paint::Rgb(r, g, b) => format!("{};2;{r};{g};{b}", if foreground { 38 } else { 48 }),
+            // the 16 colours have their own codes, older terminals don't know 38;5
+            Paint::Indexed(i @ 0..=7) => format!("{}", i + if foreground { 30 } else { 40 }),
+            Paint::Indexed(i @ 8..=15) => format!("{}", i - 8 + if foreground { 90 } else { 100 }),
+            Paint::Indexed(i) => format!("{};5;{i}", if foreground { 38 } else { 48 }),
+        }
+    }
+}
+
+/// Turns the colours of the renderer into what the terminal can show
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Palette {
+    pub depth: ColorDepth,
+    // ordered dithering, trades banding for a fine pattern
+    pub dither: bool,
+}
+
+impl Palette {
+    pub const TRUE_COLOR: Palette = Palette {
+        depth: ColorDepth::TrueColor,
+        dither: false,
+    };
+
+    /// The paint for `color` at pixel `x`, `y` of the frame
+    pub fn paint(self, color: Vec3, x: usize, y: usize) -> Paint {
+        let channel = |c: f64| c.clamp(0., 255.);
+        let offset = |spread: f64| {
+            if self.dither {
+                (BAYER[y % 4][x % 4] + 0.5) / 16. * spread - spread / 2.
+            } else {
+                0.
+            }
+        };
+        match self.depth {
+            ColorDepth::TrueColor => Paint::Rgb(color.x as u8, color.y as u8, color.z as u8),
+            ColorDepth::Ansi256 => {
+                // the cube is 40 apart except for the first step
+                let o = offset(40.);
+                let (r, g, b) = (
+                    channel(color.x + o),
+                    channel(color.y + o),
+                    channel(color.z + o),
+                );
+                Paint::Indexed(nearest_256(r, g, b))
+            }
+            ColorDepth::Ansi16 => {
+                let o = offset(100.);
+                let (r, g, b) = (
+                    channel(color.x + o),
+                    channel(color.y + o),
+                    channel(color.z + o),
+                );
+                let index = (0..16)
+                    .min_by(|&i, &j| {
+                        distance(ANSI16[i], (r, g, b)).total_cmp(&distance(ANSI16[j], (r, g, b)))
+                    })
+                    .unwrap_or(0);
+                Paint::Indexed(index as u8)
+            }
+        }
+    }
+}
+
+fn distance(a: (f64, f64, f64), b: (f64, f64, f64)) -> f64 {
+    (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2) + (a.2 - b.2).powi(2)
+}
+
+// the closest colour of the cube or the grey ramp, the 16 standard colours are left out
+// since the theme decides what they look like
+fn nearest_256(r: f64, g: f64, b: f64) -> u8 {
+    let level = |c: f64| {
+        (0..6)
+            .min_by(|&i, &j| (CUBE[i] - c).abs().total_cmp(&(CUBE[j] - c).abs()))
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (CUBE[ri], CUBE[gi], CUBE[bi]);
+    // greys 232..=255 go from 8 to 238 in steps of 10
+    let grey_index = (((r + g + b) / 3. - 8.) / 10.).round().clamp(0., 23.);
+    let grey = 8. + grey_index * 10.;
+    if distance((grey, grey, grey), (r, g, b)) < distance(cube, (r, g, b)) {
+        232 + grey_index as u8
+    } else {
+        (16 + 36 * ri + 6 * gi + bi) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb(x: f64, y: f64, z: f64) -> Vec3 {
+        Vec3 { x, y, z }
+    }
+
+    #[test]
+    fn quantizes_to_the_closest_colour() {
+        let p256 = Palette {
+            depth: ColorDepth::Ansi256,
+            dither: false,
+        };
+        assert_eq!(p256.paint(rgb(255., 0., 0.), 0, 0), Paint::Indexed(196));
+        assert_eq!(p256.paint(rgb(128., 128., 128.), 0, 0), Paint::Indexed(244));
+        let p16 = Palette {
+            depth: ColorDepth::Ansi16,
+            dither: false,
+        };
+        assert_eq!(p16.paint(rgb(250., 10., 10.), 0, 0), Paint::Indexed(9));
+        assert_eq!(p16.paint(rgb(20., 20., 20.), 0, 0), Paint::Indexed(0));
+    }
+
+    #[test]
+    fn dithering_mixes_neighbouring_colours() {
+        let palette = Palette {
+            depth: ColorDepth::Ansi256,
+            dither: true,
+        };
+        // halfway between two levels of the cube
+        let color = rgb(155., 0., 0.);
+        let paints: Vec<Paint> = (0..16)
+            .map(|i| palette.paint(color, i % 4, i / 4))
+            .collect();
+        assert!(paints.contains(&Paint::Indexed(16 + 36 * 2)));
+        assert!(paints.contains(&Paint::Indexed(16 + 36 * 3)));
+    }
+
+    #[test]
+    fn writes_escape_codes() {
+        assert_eq!(Paint::Rgb(1, 2, 3).sgr(false), "48;2;1;2;3");
+        assert_eq!(Paint::Indexed(3).sgr(true), "33");
+        assert_eq!(Paint::Indexed(9).sgr(false), "101");
+        assert_eq!(Paint::Indexed(196).sgr(true), "38;5;196");
+    }
+
+    #[test]
+    fn guesses_depth_from_the_environment() {
+        assert_eq!(
+            detect("truecolor", "xterm-256color", false),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(detect("", "screen-256color", false), ColorDepth::Ansi256);
+        assert_eq!(detect("", "linux", false), ColorDepth::Ansi16);
+        assert_eq!(detect("", "", true), ColorDepth::TrueColor);
+    }
+}
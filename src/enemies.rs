@@ -4,6 +4,8 @@ use crate::mat::*;
 #[derive(Clone)]
 pub struct Enemy {
     pos: Vec3,
+    // position before the last update, used to interpolate between ticks
+    prev_pos: Vec3,
     speed: f64,
     vel: Vec3,
     collider: BoxCollider,
@@ -19,6 +21,11 @@ impl Default for Enemy {
                 y: GH * 0.5,
                 z: GW * 0.5,
             },
+            prev_pos: Vec3 {
+                x: GW * 0.5,
+                y: GH * 0.5,
+                z: GW * 0.5,
+            },
             speed: 23.,
             vel: Vec3 {
                 x: 0.,
@@ -34,69 +41,75 @@ impl Default for Enemy {
                 min_z: -GW * 0.1,
                 tag: Some("angry_pixel"),
             },
-            mesh: Mesh::new(Vec::from([
-                //left
-                (-GW * 0.1, -GW * 0.1, -GW * 0.1),
-                (-GW * 0.1, -GW * 0.1, GW * 0.1),
-                (-GW * 0.1, GW * 0.1, GW * 0.1),
-                (255., 0., 0.),
-                (-GW * 0.1, -GW * 0.1, -GW * 0.1),
-                (-GW * 0.1, GW * 0.1, GW * 0.1),
-                (-GW * 0.1, GW * 0.1, -GW * 0.1),
-                (255., 0., 0.),
-                //front
-                (-GW * 0.1, -GW * 0.1, -GW * 0.1),
-                (GW * 0.1, -GW * 0.1, -GW * 0.1),
-                (GW * 0.1, GW * 0.1, -GW * 0.1),
-                (255., 0., 0.),
-                (-GW * 0.1, -GW * 0.1, -GW * 0.1),
-                (-GW * 0.1, GW * 0.1, -GW * 0.1),
-                (GW * 0.1, GW * 0.1, -GW * 0.1),
-                (255., 0., 0.),
-                //right
-                (GW * 0.1, -GW * 0.1, -GW * 0.1),
-                (GW * 0.1, -GW * 0.1, GW * 0.1),
-                (GW * 0.1, GW * 0.1, GW * 0.1),
-                (255., 0., 0.),
-                (GW * 0.1, -GW * 0.1, -GW * 0.1),
-                (GW * 0.1, GW * 0.1, GW * 0.1),
-                (GW * 0.1, GW * 0.1, -GW * 0.1),
-                (255., 0., 0.),
-                //back
-                (-GW * 0.1, -GW * 0.1, GW * 0.1),
-                (GW * 0.1, -GW * 0.1, GW * 0.1),
-                (GW * 0.1, GW * 0.1, GW * 0.1),
-                (255., 0., 0.),
-                (-GW * 0.1, -GW * 0.1, GW * 0.1),
-                (-GW * 0.1, GW * 0.1, GW * 0.1),
-                (GW * 0.1, GW * 0.1, GW * 0.1),
-                (255., 0., 0.),
-                //top
-                (-GW * 0.1, GW * 0.1, -GW * 0.1),
-                (-GW * 0.1, GW * 0.1, GW * 0.1),
-                (GW * 0.1, GW * 0.1, -GW * 0.1),
-                (255., 0., 0.),
-                (GW * 0.1, GW * 0.1, GW * 0.1),
-                (-GW * 0.1, GW * 0.1, GW * 0.1),
-                (GW * 0.1, GW * 0.1, -GW * 0.1),
-                (255., 0., 0.),
-                // bottom
-                (-GW * 0.1, -GW * 0.1, -GW * 0.1),
-                (-GW * 0.1, -GW * 0.1, GW * 0.1),
-                (GW * 0.1, -GW * 0.1, -GW * 0.1),
-                (255., 0., 0.),
-                (GW * 0.1, -GW * 0.1, GW * 0.1),
-                (-GW * 0.1, -GW * 0.1, GW * 0.1),
-                (GW * 0.1, -GW * 0.1, -GW * 0.1),
-                (255., 0., 0.),
-            ])),
+            mesh: cube_mesh(GW * 0.1, (255., 0., 0.)),
             vision: GW * 4.,
         }
     }
 }
 
+/// Cube around the origin, `half` is half the side length
+pub fn cube_mesh(half: f64, color: (f64, f64, f64)) -> Mesh {
+    Mesh::new(Vec::from([
+        //left
+        (-half, -half, -half),
+        (-half, -half, half),
+        (-half, half, half),
+        color,
+        (-half, -half, -half),
+        (-half, half, half),
+        (-half, half, -half),
+        color,
+        //front
+        (-half, -half, -half),
+        (half, -half, -half),
+        (half, half, -half),
+        color,
+        (-half, -half, -half),
+        (-half, half, -half),
+        (half, half, -half),
+        color,
+        //right
+        (half, -half, -half),
+        (half, -half, half),
+        (half, half, half),
+        color,
+        (half, -half, -half),
+        (half, half, half),
+        (half, half, -half),
+        color,
+        //back
+        (-half, -half, half),
+        (half, -half, half),
+        (half, half, half),
+        color,
+        (-half, -half, half),
+        (-half, half, half),
+        (half, half, half),
+        color,
+        //top
+        (-half, half, -half),
+        (-half, half, half),
+        (half, half, -half),
+        color,
+        (half, half, half),
+        (-half, half, half),
+        (half, half, -half),
+        color,
+        // bottom
+        (-half, -half, -half),
+        (-half, -half, half),
+        (half, -half, -half),
+        color,
+        (half, -half, half),
+        (-half, -half, half),
+        (half, -half, -half),
+        color,
+    ]))
+}
+
 impl Enemy {
     pub fn update(&mut self, dt: f64, player_positon: Vec3, colliders: &Vec<BoxCollider>) {
+        self.prev_pos = self.pos;
         let vec_to_player = player_positon - self.pos;
         if (vec_to_player).abs() < self.vision {
             self.vel = vec_to_player.norm() * self.speed;
@@ -120,30 +133,18 @@ impl Enemy {
 
     pub fn translate(mut self, to: Vec3) -> Self {
         self.pos = self.pos + to;
+        self.prev_pos = self.pos;
         self
     }
 
-    pub fn get_mesh(&self) -> Mesh {
+    /// Mesh at the position `alpha` of the way from the previous to the current tick
+    pub fn get_mesh(&self, alpha: f64) -> Mesh {
+        let pos = self.prev_pos + (self.pos - self.prev_pos) * alpha;
         let mut mesh = self.mesh.clone();
         for tri in mesh.mut_tris() {
-            tri.v0 = tri.v0
-                + Vec3 {
-                    x: self.pos.x,
-                    z: self.pos.z,
-                    y: self.pos.y,
-                };
-            tri.v1 = tri.v1
-                + Vec3 {
-                    x: self.pos.x,
-                    z: self.pos.z,
-                    y: self.pos.y,
-                };
-            tri.v2 = tri.v2
-                + Vec3 {
-                    x: self.pos.x,
-                    z: self.pos.z,
-                    y: self.pos.y,
-                };
+            tri.v0 = tri.v0 + pos;
+            tri.v1 = tri.v1 + pos;
+            tri.v2 = tri.v2 + pos;
         }
         mesh
     }
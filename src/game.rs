@@ -1,16 +1,24 @@
-use device_query::{DeviceQuery, DeviceState, Keycode};
+use device_query::Keycode;
 use rodio::OutputStreamHandle;
 
-use rodio::{source::Source, OutputStream};
+use rodio::OutputStream;
 
+use crate::cast::Recorder;
 use crate::enemies::*;
+use crate::ghost::Ghost;
+use crate::input::InputSource;
+use crate::keymap::{self, Action};
 use crate::loader::{self};
+use crate::output;
 use crate::renderer::{self, *};
+use crate::replay::ReplayTick;
+use crate::screenshot;
 use crate::GW;
 use crate::{audio, LevelMap};
 use crate::{camera::Camera, mat::*};
 use crate::{screens, GH};
 use core::panic;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
@@ -18,7 +26,15 @@ use std::time::{Duration, Instant};
 #[derive(Clone)]
 pub struct Game {
     pub renderer: Screen,
+    // ray caster or rasterizer, from the command line
+    pub backend: renderer::Backend,
     pub camera: Camera,
+    // every tick of the last run, saved as a replay when the level is finished
+    pub recording: Vec<ReplayTick>,
+    // asciicast file each run is drawn into, from the command line
+    pub cast_path: Option<PathBuf>,
+    // frame rate kept by rendering fewer pixels, from the settings
+    pub target_fps: Option<f64>,
 }
 
 const SPEED: f64 = 30.;
@@ -28,49 +44,293 @@ const ROTATION_SPEED: f64 = 2.5;
 const GRAVITY: f64 = 125.;
 const PLAYER_COLLIDER: ((f64, f64, f64), (f64, f64, f64)) = ((-0.2, 4.5, -0.2), (0.2, -1., 0.2));
 
+const SCREENSHOT_TEXT_TIME: Duration = Duration::from_secs(3);
+
+// simulation steps per second, independent of the frame rate
+pub const TICK_RATE: f64 = 120.;
+pub const TICK: f64 = 1. / TICK_RATE;
+// frames longer than this are slowed down instead of simulating a huge backlog of ticks
+const MAX_FRAME_TIME: f64 = 0.2;
+
+/// Keys held during one simulation tick
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct TickInput {
+    pub forward: bool,
+    pub back: bool,
+    pub left: bool,
+    pub right: bool,
+    pub jump: bool,
+    pub turn_left: bool,
+    pub turn_right: bool,
+    pub look_up: bool,
+    pub look_down: bool,
+}
+
+impl TickInput {
+    pub fn from_keys(keys: &[Keycode]) -> Self {
+        TickInput {
+            forward: keymap::pressed(keys, Action::MoveForward),
+            back: keymap::pressed(keys, Action::MoveBack),
+            left: keymap::pressed(keys, Action::MoveLeft),
+            right: keymap::pressed(keys, Action::MoveRight),
+            jump: keymap::pressed(keys, Action::Jump),
+            turn_left: keymap::pressed(keys, Action::TurnLeft),
+            turn_right: keymap::pressed(keys, Action::TurnRight),
+            look_up: keymap::pressed(keys, Action::LookUp),
+            look_down: keymap::pressed(keys, Action::LookDown),
+        }
+    }
+}
+
+/// The part of a level that changes while playing, advanced in fixed ticks
+pub struct World {
+    pub camera: Camera,
+    // camera position before the last tick, used to interpolate between ticks
+    pub prev_pos: Vec3,
+    pub enemies: Vec<Enemy>,
+    colliders: Vec<BoxCollider>,
+    pub level_timer: f64,
+    pub started: bool,
+    pub floors: usize,
+    // set by the last tick, used for sounds and recording
+    pub grounded: bool,
+    pub jumped: bool,
+    pub last_input: TickInput,
+}
+
+impl World {
+    pub fn new(map: &LevelMap, mut camera: Camera) -> Self {
+        camera.pos = Vec3 {
+            x: map.start_pos.0,
+            y: map.start_pos.1,
+            z: map.start_pos.2,
+        };
+        World {
+            prev_pos: camera.pos,
+            camera,
+            enemies: map.enemies.clone(),
+            colliders: map.colliders.clone(),
+            level_timer: 0.,
+            started: false,
+            floors: renderer::map_as_vec_of_floors(&map.map_string).len(),
+            grounded: false,
+            jumped: false,
+            last_input: TickInput::default(),
+        }
+    }
+
+    /// Advances one tick with the next input from `input`
+    pub fn step(&mut self, input: &mut dyn InputSource) -> Option<Result<f64, &'static str>> {
+        let Some(tick_input) = input.next_tick() else {
+            return Some(Err("input_ended"));
+        };
+        let result = self.tick(tick_input);
+        if let Some(rotation) = input.rotation() {
+            self.camera.rotation = rotation;
+        }
+        self.last_input = tick_input;
+        result
+    }
+
+    /// Advances the simulation by one tick.
+    /// Returns the outcome of the run once the level is finished or failed.
+    pub fn tick(&mut self, input: TickInput) -> Option<Result<f64, &'static str>> {
+        self.prev_pos = self.camera.pos;
+        self.jumped = false;
+
+        //update timer if started moving
+        if self.started {
+            self.level_timer += TICK;
+        }
+
+        //update enemies
+        let mut cols = self.colliders.clone();
+        for enemy in self.enemies.iter_mut() {
+            enemy.update(TICK, self.camera.pos, &self.colliders);
+            cols.push(enemy.get_collider());
+        }
+
+        let mut v = Vec3 {
+            x: 0.,
+            y: self.camera.vel.y,
+            z: 0.,
+        };
+
+        // handle input
+        if input.turn_left {
+            self.camera.rotation.x -= ROTATION_SPEED * TICK;
+        }
+        if input.turn_right {
+            self.camera.rotation.x += ROTATION_SPEED * TICK;
+        }
+        if input.look_up && self.camera.rotation.y < 1.5 {
+            self.camera.rotation.y += ROTATION_SPEED * TICK;
+        }
+        if input.look_down && self.camera.rotation.y > -1.5 {
+            self.camera.rotation.y -= ROTATION_SPEED * TICK;
+        }
+
+        if input.forward {
+            v = v + Vec3 {
+                x: 0.,
+                y: 0.,
+                z: SPEED,
+            }
+            .rotate_y(self.camera.rotation.x);
+            self.started = true;
+        }
+        if input.left {
+            v = v + Vec3 {
+                x: -SPEED,
+                y: 0.,
+                z: 0.,
+            }
+            .rotate_y(self.camera.rotation.x);
+            self.started = true;
+        }
+        if input.right {
+            v = v + Vec3 {
+                x: SPEED,
+                y: 0.,
+                z: 0.,
+            }
+            .rotate_y(self.camera.rotation.x);
+            self.started = true;
+        }
+        if input.back {
+            v = v + Vec3 {
+                x: 0.,
+                y: 0.,
+                z: -SPEED,
+            }
+            .rotate_y(self.camera.rotation.x);
+            self.started = true;
+        }
+
+        // add gravity
+        self.camera.vel = v;
+        self.camera.vel.y += GRAVITY * TICK;
+        self.camera.vel.y = self.camera.vel.y.min(MAX_FALL_SPEED);
+
+        // collision
+        let mut current_pc = BoxCollider::new(PLAYER_COLLIDER.0, PLAYER_COLLIDER.1, None);
+        self.grounded = false;
+        if let Some(tag) = check_collision(
+            &mut current_pc,
+            &mut self.camera.pos,
+            &mut self.camera.vel,
+            TICK,
+            &cols,
+            &mut self.grounded,
+        ) {
+            return match tag {
+                "goal" => Some(Ok(self.level_timer)),
+                "angry_pixel" => Some(Err("angry_pixel")),
+                "spike" => Some(Err("spike")),
+                t => panic!("unkown collider-tag: {t}"),
+            };
+        };
+
+        self.camera.update_pos(TICK);
+
+        // jump
+        if self.grounded && input.jump {
+            self.camera.vel.y = -JUMP_SPEED;
+            self.jumped = true;
+            self.started = true;
+        }
+
+        if self.camera.pos.y > GW * (self.floors + 10) as f64 {
+            return Some(Err("void"));
+        }
+        None
+    }
+
+    /// Camera `alpha` of the way from the previous to the current tick
+    pub fn interpolated_camera(&self, alpha: f64) -> Camera {
+        let mut camera = self.camera.clone();
+        camera.pos = self.prev_pos + (self.camera.pos - self.prev_pos) * alpha;
+        camera
+    }
+}
+
+/// Plays a level without rendering, audio or keyboard, as fast as possible.
+/// Gives up with `Err("timeout")` after `max_time` seconds of game time.
+#[cfg(test)]
+pub fn run_headless(
+    map: &LevelMap,
+    input: &mut dyn InputSource,
+    max_time: f64,
+) -> Result<f64, &'static str> {
+    let mut world = World::new(map, Camera::default());
+    for _ in 0..(max_time * TICK_RATE).ceil() as usize {
+        if let Some(result) = world.step(input) {
+            return result;
+        }
+    }
+    Err("timeout")
+}
+
 impl Game {
+    /// Plays the level until it's finished, failed or left. With `cast_path` set the
+    /// run is also recorded there, starting the file over.
     pub fn run(
         &mut self,
         map: loader::LevelMap,
         audio_handle: &OutputStreamHandle,
         focused: Arc<Mutex<bool>>,
+        input: &mut dyn InputSource,
+        ghost: Option<Ghost>,
+    ) -> Result<f64, &str> {
+        let recorder = self.cast_path.as_ref().and_then(|path| {
+            // the info bar is the extra row
+            Recorder::create(path, self.renderer.w, self.renderer.h + 1, &map.level_name).ok()
+        });
+        if let Some(recorder) = recorder {
+            output::start_recording(recorder);
+        }
+        let result = self.play(map, audio_handle, focused, input, ghost);
+        output::stop_recording();
+        result
+    }
+
+    fn play(
+        &mut self,
+        map: loader::LevelMap,
+        audio_handle: &OutputStreamHandle,
+        focused: Arc<Mutex<bool>>,
+        input: &mut dyn InputSource,
+        mut ghost: Option<Ghost>,
     ) -> Result<f64, &str> {
-        // load map files
-        // generate map meshes
+        let mut world = World::new(&map, self.camera.clone());
+        self.recording.clear();
 
         let LevelMap {
-            mesh,
-            colliders,
-            start_pos: start,
+            bvh,
             map_string,
             level_name,
-            mut enemies,
+            light,
+            ..
         } = map;
-
-        self.camera.pos = Vec3 {
-            x: start.0,
-            y: start.1,
-            z: start.2,
-        };
-
-        let mut level_timer = 0.;
+        let backend = self.backend.renderer();
 
         // timer for fps
         let mut time = Instant::now();
-
-        // device for input
-        let device_state = DeviceState::new();
-
-        let floors = renderer::map_as_vec_of_floors(&map_string).len();
+        // simulation time not yet consumed by ticks
+        let mut accumulator = 0.;
 
         // Get an output stream handle to the default physical sound device
         let (_stream, level_audio_handle) = OutputStream::try_default().unwrap();
         audio::audio_loop(&level_audio_handle, "./sounds/background.mp3");
         let walk = audio::create_infinite_sink(&level_audio_handle, "./sounds/walk.mp3");
-        walk.set_volume(30.);
+        walk.set_volume(30. * audio::volume());
         walk.pause();
 
-        let mut started = false;
+        // held keys repeat every frame, only a new press takes a screenshot
+        let mut screenshot_held = false;
+        // where the last screenshot went, shown in the info bar for a while
+        let mut screenshot_text: Option<(String, Instant)> = None;
+        let mut resolution = self.target_fps.map(ResolutionScale::new);
 
         loop {
             // reset timer for dt
@@ -79,147 +339,65 @@ impl Game {
 
             // text for infobar
             let fps_text = format!("fps: {:.2?} ", 1. / (dt));
-            let timer_text = format!("time: {:.1?} ", level_timer);
-            let floor_text = format!(
-                "floor: {}/{}",
-                (-self.camera.pos.y.div_euclid(GH) + 1.).clamp(0., floors as f64) as usize,
-                floors
-            );
-
-            // limit dt for low framerate
-            let dt = dt.min(0.2);
-            //update timer if started moving
-            if started {
-                level_timer += dt;
-            }
 
-            //update enemies
-            let mut render_mesh = mesh.clone();
-            let mut cols = colliders.clone();
-            for enemy in enemies.iter_mut() {
-                enemy.update(dt, self.camera.pos, &colliders);
-                cols.push(enemy.get_collider());
-                render_mesh = render_mesh + enemy.get_mesh();
+            if let Some(resolution) = resolution.as_mut() {
+                resolution.update(dt);
             }
 
-            // render vertices in parallel thread.
-            let cam = self.camera.clone();
-            let renderer = self.renderer.clone();
-            let render_thread =
-                thread::spawn(move || renderer.render_pruned_mt(&cam, &render_mesh));
+            // limit dt for low framerate
+            accumulator += dt.min(MAX_FRAME_TIME);
 
             // get held keys
-            let keys = device_state.get_keys();
-            // initialized velocity:w
-
-            let mut v = Vec3 {
-                x: 0.,
-                y: self.camera.vel.y,
-                z: 0.,
-            };
-
-            // handle input
-            if *focused.lock().unwrap() {
-                if keys.contains(&Keycode::E) {
-                    let time1 = time.elapsed();
+            let keys = input.keys();
 
-                    if screens::exit(focused.clone()) {
-                        return Err("menu");
-                    };
-                    time = Instant::now().checked_sub(time1).unwrap();
-                }
-                if keys.contains(&Keycode::M) {
-                    let time1 = time.elapsed();
-                    time = Instant::now();
-                    self.renderer
-                        .render_map(&map_string, self.camera.pos, loader::GW, loader::GH);
-                    loop {
-                        if device_state.get_keys().contains(&Keycode::M) {
-                            if time.elapsed() < Duration::from_millis(150) {
-                                continue;
-                            }
-                            thread::sleep_ms(150);
-                            break;
+            // handle input that isn't part of the simulation
+            if keymap::pressed(&keys, Action::Exit) {
+                if screens::exit(focused.clone()) {
+                    return Err("menu");
+                };
+                self.renderer.invalidate();
+                time = Instant::now();
+            }
+            if keymap::pressed(&keys, Action::ToggleMap) {
+                time = Instant::now();
+                self.renderer
+                    .render_map(&map_string, world.camera.pos, loader::GW, loader::GH);
+                loop {
+                    // the map stays up until it's toggled again, no need to ask all the time
+                    thread::sleep(Duration::from_millis(10));
+                    if keymap::pressed(&input.keys(), Action::ToggleMap) {
+                        if time.elapsed() < Duration::from_millis(150) {
+                            continue;
                         }
+                        thread::sleep(Duration::from_millis(150));
+                        break;
                     }
-                    time = Instant::now().checked_sub(time1).unwrap();
-                }
-                if keys.contains(&Keycode::Left) {
-                    self.camera.rotation.x -= ROTATION_SPEED * dt;
-                }
-                if keys.contains(&Keycode::Right) {
-                    self.camera.rotation.x += ROTATION_SPEED * dt;
-                }
-                if keys.contains(&Keycode::Up) && self.camera.rotation.y < 1.5 {
-                    self.camera.rotation.y += ROTATION_SPEED * dt;
-                }
-                if keys.contains(&Keycode::Down) && self.camera.rotation.y > -1.5 {
-                    self.camera.rotation.y -= ROTATION_SPEED * dt;
                 }
+                self.renderer.invalidate();
+                time = Instant::now();
+            }
+            if keymap::pressed(&keys, Action::Retry) {
+                return Err("retry");
+            }
 
-                if keys.contains(&Keycode::W) {
-                    v = v + Vec3 {
-                        x: 0.,
-                        y: 0.,
-                        z: SPEED,
-                    }
-                    .rotate_y(self.camera.rotation.x);
-                    started = true;
-                }
-                if keys.contains(&Keycode::A) {
-                    v = v + Vec3 {
-                        x: -SPEED,
-                        y: 0.,
-                        z: 0.,
-                    }
-                    .rotate_y(self.camera.rotation.x);
-                    started = true;
-                }
-                if keys.contains(&Keycode::D) {
-                    v = v + Vec3 {
-                        x: SPEED,
-                        y: 0.,
-                        z: 0.,
-                    }
-                    .rotate_y(self.camera.rotation.x);
-                    started = true;
+            // run the simulation
+            while accumulator >= TICK {
+                accumulator -= TICK;
+                let result = world.step(input);
+                self.recording.push(ReplayTick {
+                    input: world.last_input,
+                    rotation: world.camera.rotation,
+                    pos: Some(world.camera.pos),
+                });
+                if let Some(result) = result {
+                    return result;
                 }
-                if keys.contains(&Keycode::S) {
-                    v = v + Vec3 {
-                        x: 0.,
-                        y: 0.,
-                        z: -SPEED,
-                    }
-                    .rotate_y(self.camera.rotation.x);
-                    started = true;
+                if world.jumped {
+                    audio::play_audio(audio_handle, "./sounds/jump.mp3");
                 }
-                if keys.contains(&Keycode::R) {
-                    return Err("retry");
-                }
-            } // add gravity
-            self.camera.vel = v;
-            self.camera.vel.y += GRAVITY * dt;
-            self.camera.vel.y = self.camera.vel.y.min(MAX_FALL_SPEED);
-
-            // collision
-            let mut current_pc = BoxCollider::new(PLAYER_COLLIDER.0, PLAYER_COLLIDER.1, None);
-            let mut grounded = false;
-            if let Some(tag) = check_collision(
-                &mut current_pc,
-                &mut self.camera.pos,
-                &mut self.camera.vel,
-                dt,
-                &cols,
-                &mut grounded,
-            ) {
-                return match tag {
-                    "goal" => Ok(level_timer),
-                    "angry_pixel" => Err("angry_pixel"),
-                    "spike" => Err("spike"),
-                    t => panic!("unkown collider-tag: {t}"),
-                };
-            };
-            if (self.camera.vel.x != 0. || self.camera.vel.z != 0.) && grounded {
+            }
+
+            if (world.camera.vel.x != 0. || world.camera.vel.z != 0.) && world.grounded {
                 if walk.is_paused() {
                     walk.play();
                 }
@@ -227,27 +405,179 @@ impl Game {
                 walk.pause();
             }
 
-            self.camera.update_pos(dt);
-
-            //print to when rendering is finished screen
-            if let Ok(buffer) = render_thread.join() {
-                self.renderer.flush(
-                    &buffer,
-                    false,
-                    &format!("{}{}{}", &fps_text, &timer_text, &floor_text),
-                );
+            // render in between the last two ticks
+            let alpha = accumulator / TICK;
+            let cam = world.interpolated_camera(alpha);
+            // only what moves is rebuilt every frame, the level itself is in the bvh
+            let mut render_mesh = Mesh::new(vec![]);
+            for enemy in world.enemies.iter() {
+                render_mesh = render_mesh + enemy.get_mesh(alpha);
+            }
+            let mut ghost_text = String::new();
+            if let Some(ghost) = ghost.as_mut() {
+                render_mesh = render_mesh + ghost.get_mesh(world.level_timer, alpha);
+                if world.started {
+                    ghost_text = format!(
+                        "ghost: {:+.2} ",
+                        ghost.split(world.camera.pos, world.level_timer)
+                    );
+                }
             }
 
-            // jump
-            if grounded && keys.contains(&Keycode::Space) && *focused.lock().unwrap() {
-                self.camera.vel.y = -JUMP_SPEED;
-                audio::play_audio(audio_handle, "./sounds/jump.mp3");
-                started = true;
+            let mut timer_text = format!("time: {:.1?} ", world.level_timer);
+            if input.is_replay() {
+                timer_text += "(replay) ";
             }
+            let floor_text = format!(
+                "floor: {}/{}",
+                (-cam.pos.y.div_euclid(GH) + 1.).clamp(0., world.floors as f64) as usize,
+                world.floors
+            );
 
-            if self.camera.pos.y > GW * (floors + 10) as f64 {
-                return Err("void");
+            let tris = render_mesh.tris();
+            let screenshot = keymap::pressed(&keys, Action::Screenshot);
+            if screenshot && !screenshot_held {
+                let image = screenshot::capture(
+                    self.backend,
+                    &cam,
+                    &bvh,
+                    &tris,
+                    &light,
+                    self.renderer.w * screenshot::KEY_SCALE,
+                    self.renderer.h * 2 * screenshot::KEY_SCALE,
+                );
+                let path = screenshot::path(&level_name);
+                let text = match screenshot::save(&path, &image) {
+                    Ok(()) => format!(" saved {}", path.display()),
+                    Err(e) => format!(" screenshot failed: {e}"),
+                };
+                screenshot_text = Some((text, Instant::now()));
+                // saving took a while, don't count it as a frame
+                time = Instant::now();
             }
+            screenshot_held = screenshot;
+            let screenshot_text = screenshot_text
+                .as_ref()
+                .filter(|(_, at)| at.elapsed() < SCREENSHOT_TEXT_TIME)
+                .map_or("", |(text, _)| text.as_str());
+
+            // flush stretches a smaller buffer over the whole terminal
+            let scaled = resolution
+                .as_ref()
+                .filter(|resolution| resolution.factor() < 1.)
+                .map(|resolution| self.renderer.scaled(resolution.factor()));
+            let view = scaled.as_ref().unwrap_or(&self.renderer);
+            let resolution_text = scaled.as_ref().map_or(String::new(), |scaled| {
+                format!(
+                    "res: {:.0}% ",
+                    scaled.w as f64 / self.renderer.w as f64 * 100.
+                )
+            });
+            let buffer = backend.render(view, &cam, &bvh, &tris, &light);
+            self.renderer.flush(
+                &buffer,
+                &format!(
+                    "{}{}{}{}{}{}",
+                    &fps_text,
+                    &resolution_text,
+                    &timer_text,
+                    &ghost_text,
+                    &floor_text,
+                    screenshot_text
+                ),
+            );
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::input::ScriptedInput;
+    use std::path::PathBuf;
+
+    // keys held for a step: w/a/s/d move, j jumps, < and > turn the camera
+    fn held(keys: &str) -> TickInput {
+        TickInput {
+            forward: keys.contains('w'),
+            back: keys.contains('s'),
+            left: keys.contains('a'),
+            right: keys.contains('d'),
+            jump: keys.contains('j'),
+            turn_left: keys.contains('<'),
+            turn_right: keys.contains('>'),
+            ..TickInput::default()
+        }
+    }
+
+    fn script(steps: &[(usize, &str)]) -> ScriptedInput {
+        ScriptedInput::new(steps.iter().map(|&(n, keys)| (n, held(keys))).collect())
+    }
+
+    pub(crate) fn level_0() -> LevelMap {
+        loader::load(&PathBuf::from("level_pack_0/level_0.txt")).unwrap()
+    }
+
+    // finishes level 0 in a little under 20 seconds
+    pub(crate) fn level_0_run() -> ScriptedInput {
+        script(&[
+            // along the top wall and up the stairs
+            (8, "<"),
+            (52, ""),
+            (600, "w"),
+            (290, "a"),
+            (75, "wj"),
+            (50, "dj"),
+            (225, "d"),
+            // across the upper floor
+            (30, "s"),
+            (10, "sj"),
+            (92, "s"),
+            (10, ""),
+            (22, "a"),
+            (5, "saj"),
+            (67, "sa"),
+            (10, "s"),
+            (40, ""),
+            // turn so two keys move along an axis, then hop between the platforms
+            (38, "<"),
+            (27, "wa"),
+            (5, "aj"),
+            (97, "a"),
+            (10, ""),
+            (19, "sa"),
+            (5, "saj"),
+            (97, "sa"),
+            (10, ""),
+            (41, "sa"),
+            (8, "sd"),
+            (5, "saj"),
+            (55, "sa"),
+            (42, "s"),
+            (30, ""),
+            // last jump, onto the end
+            (20, "sa"),
+            (5, "sd"),
+            (5, "sdj"),
+            (200, "sd"),
+        ])
+    }
+
+    #[test]
+    fn level_0_is_completable() {
+        let time = run_headless(&level_0(), &mut level_0_run(), 60.).unwrap();
+        assert!(time < 20., "took {time}s");
+    }
+
+    #[test]
+    fn standing_still_times_out() {
+        let mut input = script(&[(10 * TICK_RATE as usize, "")]);
+        assert_eq!(run_headless(&level_0(), &mut input, 5.), Err("timeout"));
+    }
+
+    #[test]
+    fn running_out_of_input_ends_the_run() {
+        let mut input = script(&[(60, "w")]);
+        assert_eq!(run_headless(&level_0(), &mut input, 5.), Err("input_ended"));
+    }
+}
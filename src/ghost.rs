@@ -0,0 +1,83 @@
+use crate::enemies::cube_mesh;
+use crate::game::TICK;
+use crate::mat::*;
+use crate::replay::Replay;
+
+const GHOST_SIZE: f64 = 1.;
+const GHOST_COLOR: (f64, f64, f64) = (80., 200., 255.);
+const GHOST_ALPHA: f64 = 0.45;
+// the camera sits at eye height, keep the ghost from covering the view when it's close
+const GHOST_OFFSET: Vec3 = Vec3 {
+    x: 0.,
+    y: 1.5,
+    z: 0.,
+};
+// how far ahead of the last match to look for the ghost's closest position
+const SPLIT_WINDOW: usize = 240;
+
+/// Personal best run shown as a translucent cube racing the player
+pub struct Ghost {
+    trajectory: Vec<Vec3>,
+    // tick where the ghost's timer started, before that it was standing still
+    start_tick: usize,
+    mesh: Mesh,
+    // last trajectory index matched to the player, for the split
+    split_idx: usize,
+}
+
+impl Ghost {
+    /// None if the replay is too old to have positions
+    pub fn from_replay(replay: &Replay) -> Option<Ghost> {
+        let trajectory = replay
+            .ticks
+            .iter()
+            .map(|tick| tick.pos)
+            .collect::<Option<Vec<Vec3>>>()?;
+        if trajectory.is_empty() {
+            return None;
+        }
+        // the level timer only runs after the first move, so time = ticks after that
+        let start_tick = trajectory
+            .len()
+            .saturating_sub((replay.time / TICK).round() as usize);
+        Some(Ghost {
+            trajectory,
+            start_tick,
+            mesh: cube_mesh(GHOST_SIZE, GHOST_COLOR).with_alpha(GHOST_ALPHA),
+            split_idx: start_tick,
+        })
+    }
+
+    fn pos_at(&self, idx: usize) -> Vec3 {
+        self.trajectory[idx.min(self.trajectory.len() - 1)]
+    }
+
+    /// Mesh where the ghost was at `level_timer`, `alpha` of the way to the next tick
+    pub fn get_mesh(&self, level_timer: f64, alpha: f64) -> Mesh {
+        let idx = self.start_tick + (level_timer / TICK).round() as usize;
+        let (prev, next) = (self.pos_at(idx.saturating_sub(1)), self.pos_at(idx));
+        let pos = prev + (next - prev) * alpha + GHOST_OFFSET;
+        let mut mesh = self.mesh.clone();
+        for tri in mesh.mut_tris() {
+            tri.v0 = tri.v0 + pos;
+            tri.v1 = tri.v1 + pos;
+            tri.v2 = tri.v2 + pos;
+        }
+        mesh
+    }
+
+    /// Seconds the player is behind (positive) or ahead (negative) of the ghost,
+    /// comparing times at the point of the ghost's run closest to the player
+    pub fn split(&mut self, pos: Vec3, level_timer: f64) -> f64 {
+        let end = (self.split_idx + SPLIT_WINDOW).min(self.trajectory.len());
+        let mut closest = f64::MAX;
+        for idx in self.split_idx..end {
+            let dist = (self.trajectory[idx] - pos).abs();
+            if dist < closest {
+                closest = dist;
+                self.split_idx = idx;
+            }
+        }
+        level_timer - (self.split_idx - self.start_tick) as f64 * TICK
+    }
+}
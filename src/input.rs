@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crossterm::event::{
+    KeyCode, KeyEvent, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{execute, terminal};
+use device_query::{DeviceQuery, DeviceState, Keycode};
+
+use crate::game::TickInput;
+use crate::mat::Vec3;
+use crate::replay::Replay;
+
+/// Where `Game::run` gets its input from
+pub trait InputSource {
+    /// Keys held this frame, used for things outside the simulation (exit, map, retry)
+    fn keys(&mut self) -> Vec<Keycode>;
+    /// Input for the next simulation tick, None once a recorded source has run out
+    fn next_tick(&mut self) -> Option<TickInput>;
+    /// Camera rotation to force after the tick that was just handed out
+    fn rotation(&self) -> Option<Vec3> {
+        None
+    }
+    fn is_replay(&self) -> bool {
+        false
+    }
+}
+
+/// Where keys are read from, picked with `--input` at startup
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    // global keyboard state, needs X11 or the OS and sees keys of other windows too
+    Device,
+    // key events sent to the terminal, works over ssh and in tmux
+    Terminal,
+}
+
+/// Picks the input backend by name ("device" or "terminal").
+/// Without a name the terminal is used over ssh, where there is no keyboard to query.
+pub fn backend(kind: Option<&str>) -> Result<Backend, String> {
+    match kind {
+        Some("device") => Ok(Backend::Device),
+        Some("terminal") => Ok(Backend::Terminal),
+        Some(kind) => Err(format!("unknown input '{kind}', use device or terminal")),
+        None if std::env::var_os("SSH_CONNECTION").is_some() => Ok(Backend::Terminal),
+        None => Ok(Backend::Device),
+    }
+}
+
+// without release events a key counts as held this long after it was pressed,
+// long enough to bridge the delay before the terminal starts repeating it
+const FIRST_REPEAT_DELAY: Duration = Duration::from_millis(550);
+// and this long after each repeat
+const REPEAT_GAP: Duration = Duration::from_millis(100);
+
+static TERMINAL: AtomicBool = AtomicBool::new(false);
+
+// what the key events of the terminal say about the keyboard
+struct TerminalKeys {
+    // when each key was last pressed or repeated, and if it has repeated
+    held: HashMap<Keycode, (Instant, bool)>,
+    // presses since the screens last asked
+    pressed: Vec<Keycode>,
+    // the terminal reports releases (kitty keyboard protocol)
+    releases: bool,
+}
+
+static TERMINAL_KEYS: Mutex<Option<TerminalKeys>> = Mutex::new(None);
+
+thread_local! {
+    // opening the display for every query is slow, so it stays open once asked
+    static DEVICE: DeviceState = DeviceState::new();
+}
+
+fn device_keys() -> Vec<Keycode> {
+    DEVICE.with(|device| device.get_keys())
+}
+
+/// Reads keys from terminal events from now on. Call in raw mode before anything reads events,
+/// it asks the terminal for release events if it speaks the kitty keyboard protocol.
+pub fn use_terminal() {
+    let releases = terminal::supports_keyboard_enhancement().unwrap_or(false)
+        && execute!(
+            std::io::stdout(),
+            PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+            )
+        )
+        .is_ok();
+    *TERMINAL_KEYS.lock().unwrap() = Some(TerminalKeys {
+        held: HashMap::new(),
+        pressed: vec![],
+        releases,
+    });
+    TERMINAL.store(true, Ordering::Relaxed);
+}
+
+/// Gives the terminal its keyboard mode back, before quitting
+pub fn restore_terminal() {
+    if let Some(keys) = TERMINAL_KEYS.lock().unwrap().as_ref() {
+        if keys.releases {
+            let _ = execute!(std::io::stdout(), PopKeyboardEnhancementFlags);
+        }
+    }
+}
+
+/// Keeps track of a key event from the terminal
+pub fn handle_key_event(event: KeyEvent) {
+    let mut state = TERMINAL_KEYS.lock().unwrap();
+    let Some(state) = state.as_mut() else {
+        return;
+    };
+    let keys = to_keycodes(event);
+    for key in keys {
+        match event.kind {
+            KeyEventKind::Press => {
+                state.held.insert(key, (Instant::now(), false));
+                state.pressed.push(key);
+            }
+            KeyEventKind::Repeat => {
+                state.held.insert(key, (Instant::now(), true));
+                state.pressed.push(key);
+            }
+            KeyEventKind::Release => {
+                state.held.remove(&key);
+            }
+        }
+    }
+}
+
+/// Forgets every held key, a release can be missed while the terminal isn't focused
+pub fn clear_keys() {
+    if let Some(state) = TERMINAL_KEYS.lock().unwrap().as_mut() {
+        state.held.clear();
+        state.pressed.clear();
+    }
+}
+
+// the device_query keys of an event, shift included for typing names
+fn to_keycodes(event: KeyEvent) -> Vec<Keycode> {
+    let key = match event.code {
+        KeyCode::Char(c) => match c.to_ascii_lowercase() {
+            c @ 'a'..='z' => format!("{}", c.to_ascii_uppercase()).parse().ok(),
+            c @ '0'..='9' => format!("Key{c}").parse().ok(),
+            ' ' => Some(Keycode::Space),
+            '`' => Some(Keycode::Grave),
+            '-' => Some(Keycode::Minus),
+            '=' => Some(Keycode::Equal),
+            '[' => Some(Keycode::LeftBracket),
+            ']' => Some(Keycode::RightBracket),
+            '\\' => Some(Keycode::BackSlash),
+            ';' => Some(Keycode::Semicolon),
+            '\'' => Some(Keycode::Apostrophe),
+            ',' => Some(Keycode::Comma),
+            '.' => Some(Keycode::Dot),
+            '/' => Some(Keycode::Slash),
+            _ => None,
+        },
+        KeyCode::F(n) => format!("F{n}").parse().ok(),
+        KeyCode::Enter => Some(Keycode::Enter),
+        KeyCode::Esc => Some(Keycode::Escape),
+        KeyCode::Backspace => Some(Keycode::Backspace),
+        KeyCode::Tab => Some(Keycode::Tab),
+        KeyCode::Up => Some(Keycode::Up),
+        KeyCode::Down => Some(Keycode::Down),
+        KeyCode::Left => Some(Keycode::Left),
+        KeyCode::Right => Some(Keycode::Right),
+        KeyCode::Home => Some(Keycode::Home),
+        KeyCode::End => Some(Keycode::End),
+        KeyCode::PageUp => Some(Keycode::PageUp),
+        KeyCode::PageDown => Some(Keycode::PageDown),
+        KeyCode::Insert => Some(Keycode::Insert),
+        KeyCode::Delete => Some(Keycode::Delete),
+        _ => None,
+    };
+    let shift = event.modifiers.contains(KeyModifiers::SHIFT)
+        || matches!(event.code, KeyCode::Char(c) if c.is_uppercase());
+    match key {
+        Some(key) if shift => vec![key, Keycode::LShift],
+        Some(key) => vec![key],
+        None => vec![],
+    }
+}
+
+/// Keys held down right now, for playing
+pub fn held_keys(focused: bool) -> Vec<Keycode> {
+    // ignore the keyboard while the terminal isn't focused
+    if !focused {
+        return vec![];
+    }
+    if !TERMINAL.load(Ordering::Relaxed) {
+        return device_keys();
+    }
+    let mut state = TERMINAL_KEYS.lock().unwrap();
+    let Some(state) = state.as_mut() else {
+        return vec![];
+    };
+    // presses during a level aren't for the screen after it
+    state.pressed.clear();
+    if !state.releases {
+        let now = Instant::now();
+        state.held.retain(|_, (at, repeated)| {
+            now - *at
+                < if *repeated {
+                    REPEAT_GAP
+                } else {
+                    FIRST_REPEAT_DELAY
+                }
+        });
+    }
+    state.held.keys().copied().collect()
+}
+
+/// Keys for the screens. From the terminal these are the keys pressed since the last call,
+/// so a tap moves the menu once and holding a key moves it at the terminal's repeat rate.
+pub fn menu_keys(focused: bool) -> Vec<Keycode> {
+    if !focused {
+        return vec![];
+    }
+    if !TERMINAL.load(Ordering::Relaxed) {
+        return device_keys();
+    }
+    let mut state = TERMINAL_KEYS.lock().unwrap();
+    let Some(state) = state.as_mut() else {
+        return vec![];
+    };
+    let mut keys = vec![];
+    for key in state.pressed.drain(..) {
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    keys
+}
+
+/// The player at the keyboard
+pub struct KeyboardInput {
+    focused: Arc<Mutex<bool>>,
+    held: Vec<Keycode>,
+}
+
+impl KeyboardInput {
+    pub fn new(focused: Arc<Mutex<bool>>) -> Self {
+        KeyboardInput {
+            focused,
+            held: vec![],
+        }
+    }
+}
+
+impl InputSource for KeyboardInput {
+    fn keys(&mut self) -> Vec<Keycode> {
+        self.held = held_keys(*self.focused.lock().unwrap());
+        self.held.clone()
+    }
+
+    fn next_tick(&mut self) -> Option<TickInput> {
+        Some(TickInput::from_keys(&self.held))
+    }
+}
+
+/// Plays back the ticks of a replay, the keyboard can still exit or restart
+pub struct ReplayInput<'a> {
+    replay: &'a Replay,
+    tick: usize,
+    keyboard: KeyboardInput,
+}
+
+impl<'a> ReplayInput<'a> {
+    pub fn new(replay: &'a Replay, focused: Arc<Mutex<bool>>) -> Self {
+        ReplayInput {
+            replay,
+            tick: 0,
+            keyboard: KeyboardInput::new(focused),
+        }
+    }
+}
+
+impl InputSource for ReplayInput<'_> {
+    fn keys(&mut self) -> Vec<Keycode> {
+        self.keyboard.keys()
+    }
+
+    fn next_tick(&mut self) -> Option<TickInput> {
+        let tick = self.replay.ticks.get(self.tick)?;
+        self.tick += 1;
+        Some(tick.input)
+    }
+
+    fn rotation(&self) -> Option<Vec3> {
+        Some(self.replay.ticks.get(self.tick.checked_sub(1)?)?.rotation)
+    }
+
+    fn is_replay(&self) -> bool {
+        true
+    }
+}
+
+/// A fixed sequence of inputs, each held for a number of ticks. Used for headless runs.
+#[cfg(test)]
+pub struct ScriptedInput {
+    steps: Vec<(usize, TickInput)>,
+    step: usize,
+    ticks_in_step: usize,
+}
+
+#[cfg(test)]
+impl ScriptedInput {
+    pub fn new(steps: Vec<(usize, TickInput)>) -> Self {
+        ScriptedInput {
+            steps,
+            step: 0,
+            ticks_in_step: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+impl InputSource for ScriptedInput {
+    fn keys(&mut self) -> Vec<Keycode> {
+        vec![]
+    }
+
+    fn next_tick(&mut self) -> Option<TickInput> {
+        while self.ticks_in_step >= self.steps.get(self.step)?.0 {
+            self.step += 1;
+            self.ticks_in_step = 0;
+        }
+        self.ticks_in_step += 1;
+        Some(self.steps[self.step].1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn terminal_keys_become_keycodes() {
+        let key = |code| to_keycodes(KeyEvent::new(code, KeyModifiers::NONE));
+        assert_eq!(key(KeyCode::Char('w')), vec![Keycode::W]);
+        assert_eq!(key(KeyCode::Char('W')), vec![Keycode::W, Keycode::LShift]);
+        assert_eq!(key(KeyCode::Char('7')), vec![Keycode::Key7]);
+        assert_eq!(key(KeyCode::Char(' ')), vec![Keycode::Space]);
+        assert_eq!(key(KeyCode::Esc), vec![Keycode::Escape]);
+        assert_eq!(key(KeyCode::F(5)), vec![Keycode::F5]);
+        assert_eq!(key(KeyCode::Char('é')), vec![]);
+    }
+
+    #[test]
+    fn picks_backend_by_name() {
+        assert_eq!(backend(Some("terminal")), Ok(Backend::Terminal));
+        assert_eq!(backend(Some("device")), Ok(Backend::Device));
+        assert!(backend(Some("joystick")).is_err());
+    }
+}
@@ -0,0 +1,260 @@
+use std::sync::RwLock;
+
+use device_query::Keycode;
+use serde_json::{Map, Value};
+
+/// Everything a key does, in the game and on the screens around it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    TurnLeft,
+    TurnRight,
+    LookUp,
+    LookDown,
+    ToggleMap,
+    Retry,
+    Screenshot,
+    Exit,
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight,
+    Confirm,
+    Back,
+    Leaderboard,
+    WatchReplay,
+    Campaign,
+    Settings,
+}
+
+impl Action {
+    /// In the order of the rebinding screen
+    pub const ALL: [Action; 23] = [
+        Action::MoveForward,
+        Action::MoveBack,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Jump,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::LookUp,
+        Action::LookDown,
+        Action::ToggleMap,
+        Action::Retry,
+        Action::Screenshot,
+        Action::Exit,
+        Action::MenuUp,
+        Action::MenuDown,
+        Action::MenuLeft,
+        Action::MenuRight,
+        Action::Confirm,
+        Action::Back,
+        Action::Leaderboard,
+        Action::WatchReplay,
+        Action::Campaign,
+        Action::Settings,
+    ];
+
+    /// Key in the settings file
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveForward => "move_forward",
+            Action::MoveBack => "move_back",
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::Jump => "jump",
+            Action::TurnLeft => "turn_left",
+            Action::TurnRight => "turn_right",
+            Action::LookUp => "look_up",
+            Action::LookDown => "look_down",
+            Action::ToggleMap => "toggle_map",
+            Action::Retry => "retry",
+            Action::Screenshot => "screenshot",
+            Action::Exit => "exit",
+            Action::MenuUp => "menu_up",
+            Action::MenuDown => "menu_down",
+            Action::MenuLeft => "menu_left",
+            Action::MenuRight => "menu_right",
+            Action::Confirm => "confirm",
+            Action::Back => "back",
+            Action::Leaderboard => "leaderboard",
+            Action::WatchReplay => "watch_replay",
+            Action::Campaign => "campaign",
+            Action::Settings => "settings",
+        }
+    }
+
+    /// Shown on the rebinding screen
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::MoveForward => "Move forward",
+            Action::MoveBack => "Move back",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::Jump => "Jump",
+            Action::TurnLeft => "Turn left",
+            Action::TurnRight => "Turn right",
+            Action::LookUp => "Look up",
+            Action::LookDown => "Look down",
+            Action::ToggleMap => "View map",
+            Action::Retry => "Retry level",
+            Action::Screenshot => "Screenshot",
+            Action::Exit => "Exit",
+            Action::MenuUp => "Menu up",
+            Action::MenuDown => "Menu down",
+            Action::MenuLeft => "Menu left",
+            Action::MenuRight => "Menu right",
+            Action::Confirm => "Confirm",
+            Action::Back => "Back",
+            Action::Leaderboard => "Leaderboard",
+            Action::WatchReplay => "Watch best run",
+            Action::Campaign => "Campaign mode",
+            Action::Settings => "Settings",
+        }
+    }
+
+    // two actions can't share a key if they are used at the same time
+    fn in_game(self) -> bool {
+        (self as usize) <= Action::Exit as usize
+    }
+
+    fn in_menu(self) -> bool {
+        self as usize >= Action::Exit as usize
+    }
+}
+
+/// Which key does each action, indexed by `Action as usize`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keymap([Keycode; Action::ALL.len()]);
+
+impl Keymap {
+    pub const DEFAULT: Keymap = Keymap([
+        Keycode::W,
+        Keycode::S,
+        Keycode::A,
+        Keycode::D,
+        Keycode::Space,
+        Keycode::Left,
+        Keycode::Right,
+        Keycode::Up,
+        Keycode::Down,
+        Keycode::M,
+        Keycode::R,
+        Keycode::F12,
+        Keycode::E,
+        Keycode::Up,
+        Keycode::Down,
+        Keycode::Left,
+        Keycode::Right,
+        Keycode::Enter,
+        Keycode::Escape,
+        Keycode::L,
+        Keycode::P,
+        Keycode::C,
+        Keycode::O,
+    ]);
+
+    pub fn key(&self, action: Action) -> Keycode {
+        self.0[action as usize]
+    }
+
+    /// Binds `key` to `action`. An action it clashes with gets the old key of `action`.
+    pub fn bind(&mut self, action: Action, key: Keycode) {
+        let old = self.key(action);
+        for other in Action::ALL {
+            let shared =
+                (other.in_game() && action.in_game()) || (other.in_menu() && action.in_menu());
+            if other != action && shared && self.key(other) == key {
+                self.0[other as usize] = old;
+            }
+        }
+        self.0[action as usize] = key;
+    }
+
+    pub fn to_json(self) -> Value {
+        let keys: Map<String, Value> = Action::ALL
+            .iter()
+            .map(|action| {
+                (
+                    action.name().to_string(),
+                    self.key(*action).to_string().into(),
+                )
+            })
+            .collect();
+        Value::Object(keys)
+    }
+
+    /// Actions missing from `value` or with an unknown key keep the default
+    pub fn from_json(value: &Value) -> Keymap {
+        let mut keymap = Keymap::DEFAULT;
+        for action in Action::ALL {
+            let key = value
+                .get(action.name())
+                .and_then(Value::as_str)
+                .and_then(|key| key.parse::<Keycode>().ok());
+            if let Some(key) = key {
+                keymap.0[action as usize] = key;
+            }
+        }
+        keymap
+    }
+}
+
+// the keymap from the settings, read wherever keys are checked
+static ACTIVE: RwLock<Keymap> = RwLock::new(Keymap::DEFAULT);
+
+pub fn set_active(keymap: Keymap) {
+    *ACTIVE.write().unwrap() = keymap;
+}
+
+pub fn key(action: Action) -> Keycode {
+    ACTIVE.read().unwrap().key(action)
+}
+
+/// Whether the key of `action` is among the held `keys`
+pub fn pressed(keys: &[Keycode], action: Action) -> bool {
+    keys.contains(&key(action))
+}
+
+/// How a key is written in the menus, arrows as arrows
+pub fn label(action: Action) -> String {
+    match key(action) {
+        Keycode::Up => "\u{1F845} ".to_string(),
+        Keycode::Down => "\u{1F847} ".to_string(),
+        Keycode::Left => "\u{1F844} ".to_string(),
+        Keycode::Right => "\u{1F846} ".to_string(),
+        Keycode::Space => "SPACE".to_string(),
+        key => key.to_string().to_uppercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn rebinding_swaps_clashing_keys() {
+        let mut keymap = Keymap::DEFAULT;
+        keymap.bind(Action::Jump, Keycode::W);
+        assert_eq!(keymap.key(Action::Jump), Keycode::W);
+        assert_eq!(keymap.key(Action::MoveForward), Keycode::Space);
+        // menu keys may share a key with movement
+        keymap.bind(Action::Leaderboard, Keycode::A);
+        assert_eq!(keymap.key(Action::MoveLeft), Keycode::A);
+    }
+
+    #[test]
+    fn keymap_survives_json() {
+        let mut keymap = Keymap::DEFAULT;
+        keymap.bind(Action::MoveForward, Keycode::Z);
+        assert_eq!(Keymap::from_json(&keymap.to_json()), keymap);
+        let partial = Keymap::from_json(&json!({"jump": "J", "retry": "NotAKey"}));
+        assert_eq!(partial.key(Action::Jump), Keycode::J);
+        assert_eq!(partial.key(Action::Retry), Keycode::R);
+    }
+}
@@ -0,0 +1,183 @@
+use serde_json::Value;
+
+use crate::mat::{Tri, Vec3};
+
+/// How a level is lit: a sun far away in one direction, some light from everywhere and a
+/// fog the level fades into with distance. Set per level in the pack manifest.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    // towards the sun, y is down like everywhere in the level
+    pub sun: Vec3,
+    // added to a face turned straight at the sun
+    pub sun_strength: f64,
+    // every face gets this much, in the sun or not
+    pub ambient: f64,
+    // colour of the fog, also what is seen where nothing is hit
+    pub fog: Vec3,
+    // fraction of the view distance that is clear before the fog thickens, from the settings
+    pub fog_start: f64,
+    // whether the level casts shadows, a setting of the player rather than of the level
+    pub shadows: bool,
+}
+
+impl Default for Light {
+    // high up and a little to the side so the tops of blocks are the brightest faces
+    fn default() -> Self {
+        Light {
+            sun: Vec3 {
+                x: 0.4,
+                y: -1.,
+                z: 0.25,
+            }
+            .norm(),
+            sun_strength: 0.55,
+            ambient: 0.45,
+            fog: Vec3 {
+                x: 0.,
+                y: 0.,
+                z: 0.,
+            },
+            fog_start: 0.,
+            shadows: false,
+        }
+    }
+}
+
+impl Light {
+    /// Colour of `tri` seen along `ray_dir`. With shadows on, `shadowed` is asked whether
+    /// something is between the hit and the sun, only for faces turned towards it.
+    pub fn shade(&self, tri: &Tri, ray_dir: Vec3, shadowed: impl FnOnce() -> bool) -> Vec3 {
+        let mut n = tri.normal();
+        // tris have no front side, the one facing the camera is lit
+        if n.dot(ray_dir) > 0. {
+            n = n * -1.;
+        }
+        let mut sun = n.dot(self.sun).max(0.);
+        if sun > 0. && self.shadows && shadowed() {
+            sun = 0.;
+        }
+        tri.color * (self.ambient + self.sun_strength * sun)
+    }
+
+    /// Fades `color` into the fog, `distance` is the fraction of the view distance it is
+    /// away. Nothing before `fog_start`, then evenly thicker up to all fog at the end.
+    pub fn fog(&self, color: Vec3, distance: f64) -> Vec3 {
+        let fraction = ((distance - self.fog_start) / (1. - self.fog_start)).clamp(0., 1.);
+        color * (1. - fraction) + self.fog * fraction
+    }
+
+    // {"sun": [x, y, z], "sun_strength": .., "ambient": .., "fog": [r, g, b]}, all optional
+    pub fn from_json(value: &Value) -> Result<Light, String> {
+        let default = Light::default();
+        let number = |key: &str, default: f64| match value.get(key) {
+            Some(v) => v
+                .as_f64()
+                .filter(|v| *v >= 0.)
+                .ok_or(format!("light {key} must be a number of at least 0")),
+            None => Ok(default),
+        };
+        let vector = |key: &str, default: Vec3| match value.get(key) {
+            Some(v) => {
+                let xyz: Option<Vec<f64>> = v
+                    .as_array()
+                    .and_then(|v| v.iter().map(Value::as_f64).collect());
+                match xyz.as_deref() {
+                    Some(&[x, y, z]) => Ok(Vec3 { x, y, z }),
+                    _ => Err(format!("light {key} must be a list of 3 numbers")),
+                }
+            }
+            None => Ok(default),
+        };
+        let sun = vector("sun", default.sun)?;
+        if sun.abs() == 0. {
+            return Err("light sun can't be [0, 0, 0]".to_string());
+        }
+        Ok(Light {
+            sun: sun.norm(),
+            sun_strength: number("sun_strength", default.sun_strength)?,
+            ambient: number("ambient", default.ambient)?,
+            fog: vector("fog", default.fog)?,
+            fog_start: default.fog_start,
+            shadows: default.shadows,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mat::Mesh;
+    use serde_json::json;
+
+    fn grey_tri(v0: (f64, f64, f64), v1: (f64, f64, f64), v2: (f64, f64, f64)) -> Tri {
+        Mesh::new(vec![v0, v1, v2, (100., 100., 100.)]).tris()[0]
+    }
+
+    // flat on the ground, the normal up or down
+    fn tri(normal_up: bool) -> Tri {
+        let (a, b) = if normal_up { (1., 0.) } else { (0., 1.) };
+        grey_tri((0., 0., 0.), (a, 0., b), (b, 0., a))
+    }
+
+    #[test]
+    fn faces_towards_the_sun_are_brighter() {
+        let light = Light::default();
+        let down = Vec3 {
+            x: 0.,
+            y: 1.,
+            z: 0.,
+        };
+        let floor = light.shade(&tri(true), down, || false);
+        // either winding is lit the same
+        assert_eq!(floor, light.shade(&tri(false), down, || false));
+        let wall = grey_tri((0., 0., 0.), (0., 1., 0.), (0., 0., 1.));
+        let sideways = Vec3 {
+            x: 1.,
+            y: 0.,
+            z: 0.,
+        };
+        assert!(floor.x > light.shade(&wall, sideways, || false).x);
+        // seen from below the sun doesn't reach it
+        let below = light.shade(&tri(true), down * -1., || panic!("not facing the sun"));
+        assert_eq!(below.x, 100. * light.ambient);
+    }
+
+    #[test]
+    fn shadows_leave_only_ambient() {
+        let down = Vec3 {
+            x: 0.,
+            y: 1.,
+            z: 0.,
+        };
+        let light = Light {
+            shadows: true,
+            ..Light::default()
+        };
+        assert_eq!(
+            light.shade(&tri(true), down, || true).x,
+            100. * light.ambient
+        );
+        assert!(light.shade(&tri(true), down, || false).x > 100. * light.ambient);
+        // switched off nothing is asked
+        let off = Light::default();
+        assert!(off.shade(&tri(true), down, || panic!("shadows are off")).x > 100. * off.ambient);
+    }
+
+    #[test]
+    fn reads_manifest_values() {
+        let light = Light::from_json(&json!({"ambient": 0.2, "fog": [10, 20, 30]})).unwrap();
+        assert_eq!(light.ambient, 0.2);
+        assert_eq!(light.sun_strength, Light::default().sun_strength);
+        assert_eq!(light.fog.z, 30.);
+        assert_eq!(light.fog(light.fog * 0., 1.).y, 20.);
+        let half = Light {
+            fog_start: 0.5,
+            ..light
+        };
+        assert_eq!(half.fog(light.fog * 0., 0.5).y, 0.);
+        assert_eq!(half.fog(light.fog * 0., 0.75).y, 10.);
+        assert!(Light::from_json(&json!({"sun": [0, 0, 0]})).is_err());
+        assert!(Light::from_json(&json!({"fog": "grey"})).is_err());
+        assert!(Light::from_json(&json!({"ambient": -1})).is_err());
+    }
+}
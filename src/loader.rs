@@ -1,19 +1,27 @@
+use crate::bvh::Bvh;
 use crate::enemies::Enemy;
+use crate::light::Light;
 use crate::mat::*;
+use crate::texture::Texture;
+use std::fmt;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 pub const GW: f64 = 10.;
 pub const GH: f64 = 15.;
 
 #[derive(Clone)]
 pub struct LevelMap {
-    pub mesh: Mesh,
+    // the level mesh, sorted for ray casting. Shared so retrying doesn't rebuild it
+    pub bvh: Arc<Bvh>,
     pub colliders: Vec<BoxCollider>,
     pub start_pos: (f64, f64, f64),
     pub map_string: String,
     pub level_name: String,
     pub enemies: Vec<Enemy>,
+    // from the pack manifest, the default until the level is picked from its pack
+    pub light: Light,
 }
 
 const WALL: [[(f64, f64, f64); 8]; 6] = [
@@ -399,32 +407,99 @@ const SPIKE: [(f64, f64, f64); 4 * 4] = [
     (100., 100., 100.),
 ];
 
-fn separate_map(map: &str) -> Vec<String> {
+/// Id used for leaderboards and replays, name + crc32(map) so edited levels get a new id
+pub fn level_id(level_name: &str, map_string: &str) -> String {
+    level_name.to_string() + &crc32fast::hash(map_string.as_bytes()).to_string()
+}
+
+impl LevelMap {
+    pub fn id(&self) -> String {
+        level_id(&self.level_name, &self.map_string)
+    }
+}
+
+/// Where a character sits in a level file, all counted from 1
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MapPos {
+    pub floor: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl fmt::Display for MapPos {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {} (floor {})",
+            self.line, self.col, self.floor
+        )
+    }
+}
+
+/// Why a level couldn't be loaded
+#[derive(Clone, Debug, PartialEq)]
+pub enum LevelError {
+    Unreadable(String),
+    InvalidCharacter(char, MapPos),
+    MissingStart,
+    MultipleStarts(MapPos, MapPos),
+    MissingEnd,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LevelError::Unreadable(e) => write!(f, "couldn't read level: {e}"),
+            LevelError::InvalidCharacter(c, pos) => write!(f, "invalid character '{c}' at {pos}"),
+            LevelError::MissingStart => write!(f, "level has no start (S)"),
+            LevelError::MultipleStarts(first, second) => {
+                write!(
+                    f,
+                    "level has a second start (S) at {second}, first at {first}"
+                )
+            }
+            LevelError::MissingEnd => write!(f, "level has no end (E)"),
+        }
+    }
+}
+
+// floors of the map, with the index of the line each one starts at
+pub fn separate_map(map: &str) -> Vec<(usize, String)> {
     let mut cur = String::new();
+    let mut start = 0;
     let mut res = vec![];
-    for line in map.lines() {
+    for (i, line) in map.lines().enumerate() {
         if line.trim() != "sep" {
             cur += line;
             cur += "\n";
         } else {
-            res.push(cur.clone());
+            res.push((start, cur.clone()));
             cur = String::new();
+            start = i + 1;
         }
     }
-    res.push(cur);
+    res.push((start, cur));
     res
 }
 
-pub fn load(path: &PathBuf) -> LevelMap {
+pub fn load(path: &PathBuf) -> Result<LevelMap, LevelError> {
+    let map_string = fs::read_to_string(path).map_err(|e| LevelError::Unreadable(e.to_string()))?;
+    let level_name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    parse(level_name, map_string)
+}
+
+pub fn parse(level_name: String, map_string: String) -> Result<LevelMap, LevelError> {
     let mut mesh = Mesh::new([].into());
-    let mut start = (0., 0., 0.);
+    let mut start = None;
+    let mut has_end = false;
     let mut colliders: Vec<BoxCollider> = vec![];
-    let map_string = fs::read_to_string(path).expect("couldn't read level");
-    let level_name = path.file_stem().unwrap().to_str().unwrap().to_owned();
     let sep_map = separate_map(&map_string);
     let maps = sep_map
         .iter()
-        .map(|x| x.split("\n").map(|y| y.trim_end()).collect::<Vec<_>>())
+        .map(|(_, x)| x.split("\n").map(|y| y.trim_end()).collect::<Vec<_>>())
         .collect::<Vec<_>>();
     let mut enemies: Vec<Enemy> = vec![];
     for (level, map) in maps.iter().enumerate() {
@@ -434,6 +509,11 @@ pub fn load(path: &PathBuf) -> LevelMap {
                 continue;
             }
             for (x, ch) in row.chars().enumerate() {
+                let pos = MapPos {
+                    floor: level + 1,
+                    line: sep_map[level].0 + z + 1,
+                    col: x + 1,
+                };
                 let mut grid = Mesh::new(vec![]);
                 let mut colliders_grid: Vec<BoxCollider> = vec![];
                 match ch {
@@ -465,11 +545,17 @@ pub fn load(path: &PathBuf) -> LevelMap {
                     }
 
                     'S' => {
-                        start = (
-                            x as f64 * GW + GW / 2.,
-                            level as f64 * GH + GH * 0.5,
-                            z as f64 * GW + GW / 2.,
-                        );
+                        if let Some((_, first)) = start {
+                            return Err(LevelError::MultipleStarts(first, pos));
+                        }
+                        start = Some((
+                            (
+                                x as f64 * GW + GW / 2.,
+                                level as f64 * GH + GH * 0.5,
+                                z as f64 * GW + GW / 2.,
+                            ),
+                            pos,
+                        ));
                         grid = Mesh::new(Vec::from(START));
                         colliders_grid.push(BoxCollider::new(
                             START_COLLIDER[0],
@@ -479,6 +565,7 @@ pub fn load(path: &PathBuf) -> LevelMap {
                     }
 
                     'E' => {
+                        has_end = true;
                         grid = Mesh::new(Vec::from(END));
                         colliders_grid.push(BoxCollider::new(
                             FLOOR_COLLIDER[0],
@@ -492,7 +579,7 @@ pub fn load(path: &PathBuf) -> LevelMap {
                         ))
                     }
 
-                    c => panic!("invalid character, {c}",),
+                    c => return Err(LevelError::InvalidCharacter(c, pos)),
                 }
 
                 // Translating grid to position
@@ -531,14 +618,26 @@ pub fn load(path: &PathBuf) -> LevelMap {
             }
         }
     }
-    LevelMap {
-        mesh,
+    let Some((start_pos, _)) = start else {
+        return Err(LevelError::MissingStart);
+    };
+    if !has_end {
+        return Err(LevelError::MissingEnd);
+    }
+    Ok(LevelMap {
+        bvh: Arc::new(Bvh::new(mesh.tris())),
         colliders,
-        start_pos: start,
+        start_pos,
         map_string,
         level_name,
         enemies,
-    }
+        light: Light::default(),
+    })
+}
+
+// a face from one of the tables above with its pattern
+fn tile(vertices: &[(f64, f64, f64)], texture: Texture) -> Mesh {
+    Mesh::new(vertices.to_vec()).with_texture(texture)
 }
 
 fn add_spike(mut grid: Mesh, colliders: &mut Vec<BoxCollider>) -> Mesh {
@@ -568,30 +667,30 @@ fn add_wall(
             .contains(&maps[level + 1][z].chars().nth(x))
     {
         // add top wall
-        grid = grid + Mesh::new(Vec::from(WALL[0]));
+        grid = grid + tile(&WALL[0], Texture::Brick);
     }
     if level != 0
         && maps[level - 1].get(z).is_some()
         && maps[level - 1][z].chars().nth(x) != Some('X')
     {
         //add under-wall
-        grid = grid + Mesh::new(Vec::from(WALL[1]));
+        grid = grid + tile(&WALL[1], Texture::Brick);
     }
     if z == 0 || rows[z - 1].chars().nth(x) != Some('X') {
         // add upper wall
-        grid = grid + Mesh::new(Vec::from(WALL[2]));
+        grid = grid + tile(&WALL[2], Texture::Brick);
     }
     if z == rows.len() - 1 || rows[z + 1].chars().nth(x) != Some('X') {
         // add bottom wall
-        grid = grid + Mesh::new(Vec::from(WALL[3]));
+        grid = grid + tile(&WALL[3], Texture::Brick);
     }
     if x == 0 || rows[z].chars().nth(x - 1) != Some('X') {
         // add left wall
-        grid = grid + Mesh::new(Vec::from(WALL[4]));
+        grid = grid + tile(&WALL[4], Texture::Brick);
     }
     if x == row.len() - 1 || rows[z].chars().nth(x + 1) != Some('X') {
         // add right wall
-        grid = grid + Mesh::new(Vec::from(WALL[5]));
+        grid = grid + tile(&WALL[5], Texture::Brick);
     }
 
     colliders_grid.push(BoxCollider::new(WALL_COLLIDER[0], WALL_COLLIDER[1], None));
@@ -610,30 +709,30 @@ fn add_half_wall(
 ) -> Mesh {
     // Adding the visible face
     // add top wall
-    grid = grid + Mesh::new(Vec::from(HALF_WALL[0]));
+    grid = grid + tile(&HALF_WALL[0], Texture::Brick);
     if level != 0
         && maps[level - 1].get(z).is_some()
         && maps[level - 1][z].chars().nth(x) != Some('X')
     {
         //add under-wall
-        grid = grid + Mesh::new(Vec::from(HALF_WALL[1]));
+        grid = grid + tile(&HALF_WALL[1], Texture::Brick);
     }
 
     if z == 0 || rows[z - 1].chars().nth(x) != Some('X') {
         // add upper wall
-        grid = grid + Mesh::new(Vec::from(HALF_WALL[2]));
+        grid = grid + tile(&HALF_WALL[2], Texture::Brick);
     }
     if z == rows.len() - 1 || rows[z + 1].chars().nth(x) != Some('X') {
         // add bottom wall
-        grid = grid + Mesh::new(Vec::from(HALF_WALL[3]));
+        grid = grid + tile(&HALF_WALL[3], Texture::Brick);
     }
     if x == 0 || rows[z].chars().nth(x - 1) != Some('X') {
         // add left wall
-        grid = grid + Mesh::new(Vec::from(HALF_WALL[4]));
+        grid = grid + tile(&HALF_WALL[4], Texture::Brick);
     }
     if x == row.len() - 1 || rows[z].chars().nth(x + 1) != Some('X') {
         // add right wall
-        grid = grid + Mesh::new(Vec::from(HALF_WALL[5]));
+        grid = grid + tile(&HALF_WALL[5], Texture::Brick);
     }
 
     colliders_grid.push(BoxCollider::new(
@@ -655,25 +754,25 @@ fn add_floor(
 ) -> Mesh {
     // add floor
     // add lower section (roof)
-    grid = grid + Mesh::new(Vec::from(FLOOR[0]));
+    grid = grid + tile(&FLOOR[0], Texture::Checker);
     if level != 0 {
-        grid = grid + Mesh::new(Vec::from(FLOOR[1]));
+        grid = grid + tile(&FLOOR[1], Texture::Checker);
     }
     if x == 0 || row.chars().nth(x - 1) != Some('.') {
         // add left floor wall
-        grid = grid + Mesh::new(Vec::from(FLOOR[2]));
+        grid = grid + tile(&FLOOR[2], Texture::Checker);
     }
     if x == row.len() - 1 || row.chars().nth(x + 1) != Some('.') {
         // add right floor wall
-        grid = grid + Mesh::new(Vec::from(FLOOR[3]));
+        grid = grid + tile(&FLOOR[3], Texture::Checker);
     }
     if z == 0 || rows[z - 1].chars().nth(x) != Some('.') {
         // add front floor wall
-        grid = grid + Mesh::new(Vec::from(FLOOR[4]));
+        grid = grid + tile(&FLOOR[4], Texture::Checker);
     }
     if z == rows.len() - 1 || rows[z + 1].chars().nth(x) != Some('.') {
         // add back floor wall
-        grid = grid + Mesh::new(Vec::from(FLOOR[5]));
+        grid = grid + tile(&FLOOR[5], Texture::Checker);
     }
 
     // add collider to colliders
@@ -682,11 +781,52 @@ fn add_floor(
     grid
 }
 
-// #[cfg(test)]
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-// mod tests {
-//     use super::*;
+    fn parse_str(map: &str) -> Result<LevelMap, LevelError> {
+        parse("test".to_string(), map.to_string())
+    }
+
+    #[test]
+    fn bundled_levels_load() {
+        for entry in fs::read_dir("level_pack_0").unwrap() {
+            let path = entry.unwrap().path();
+            if let Err(e) = load(&path) {
+                panic!("{}: {e}", path.display());
+            }
+        }
+    }
 
-//     #[test]
+    #[test]
+    fn invalid_character_has_position() {
+        let err = parse_str("XXXX\nXS.X\nsep\nXXXX\nX.qE\n").err();
+        let pos = MapPos {
+            floor: 2,
+            line: 5,
+            col: 3,
+        };
+        assert_eq!(err, Some(LevelError::InvalidCharacter('q', pos)));
+    }
 
-// }
+    #[test]
+    fn start_and_end_are_required() {
+        assert_eq!(parse_str("X..E").err(), Some(LevelError::MissingStart));
+        assert_eq!(parse_str("XS.X").err(), Some(LevelError::MissingEnd));
+        let first = MapPos {
+            floor: 1,
+            line: 1,
+            col: 2,
+        };
+        let second = MapPos {
+            floor: 1,
+            line: 2,
+            col: 3,
+        };
+        assert_eq!(
+            parse_str("XS.E\nX.S.").err(),
+            Some(LevelError::MultipleStarts(first, second))
+        );
+    }
+}
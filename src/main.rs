@@ -1,75 +1,398 @@
 use crossterm::event::{poll, read, Event};
 use crossterm::{self};
 use device_query::{self};
+use ghost::Ghost;
+use input::{InputSource, KeyboardInput, ReplayInput};
+use light::Light;
 use loader::*;
-use mat::Vec3;
+use profile::{Overrides, Profile};
+use progress::Progress;
 use renderer::Screen;
+use replay::Replay;
 use rodio::OutputStream;
+use screens::{MenuChoice, MenuState};
 use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread;
 use std::time::Duration;
 
 mod audio;
+mod bench;
+mod bvh;
 mod camera;
+mod cast;
+mod check;
+mod color;
 mod enemies;
 mod game;
+mod ghost;
+mod input;
+mod keymap;
+mod light;
 mod loader;
 mod mat;
 mod network;
+mod output;
+mod pack;
+mod paths;
+mod profile;
+mod progress;
+mod raster;
 mod renderer;
+mod replay;
 mod screens;
+mod screenshot;
+mod texture;
+
+struct Args {
+    level_dir: String,
+    leaderboard: Option<String>,
+    replay: Option<String>,
+    input: Option<String>,
+    renderer: Option<String>,
+    colors: Option<String>,
+    dither: bool,
+    output: Option<String>,
+    record: Option<String>,
+    fov: Option<String>,
+    view_distance: Option<String>,
+    fog_start: Option<String>,
+    target_fps: Option<String>,
+}
+
+fn parse_args() -> Args {
+    let mut args = env::args().skip(1);
+    let mut level_dir = None;
+    let mut leaderboard = None;
+    let mut replay = None;
+    let mut input = None;
+    let mut renderer = None;
+    let mut colors = None;
+    let mut dither = false;
+    let mut output = None;
+    let mut record = None;
+    let mut fov = None;
+    let mut view_distance = None;
+    let mut fog_start = None;
+    let mut target_fps = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--leaderboard" => leaderboard = args.next(),
+            "--replay" => replay = args.next(),
+            "--input" => input = args.next(),
+            "--renderer" => renderer = args.next(),
+            "--colors" => colors = args.next(),
+            "--dither" => dither = true,
+            "--output" => output = args.next(),
+            "--record" => record = args.next(),
+            "--fov" => fov = args.next(),
+            "--view-distance" => view_distance = args.next(),
+            "--fog-start" => fog_start = args.next(),
+            "--target-fps" => target_fps = args.next(),
+            _ => level_dir = Some(arg),
+        }
+    }
+    let Some(level_dir) = level_dir else {
+        eprintln!(
+            "usage: termtrack <level_folder> [--leaderboard online|local] [--replay <file>] [--input device|terminal] [--renderer raycast|raster] [--colors auto|truecolor|256|16] [--dither] [--output <file>] [--record <file.cast>] [--fov <degrees>] [--view-distance <units>] [--fog-start <0-0.9>] [--target-fps <fps, 0 is off>]"
+        );
+        eprintln!("       termtrack check <level_file_or_folder>...");
+        eprintln!("       termtrack bench <level_file> [frames]");
+        eprintln!("       termtrack screenshot <level_file> <image.png|image.ppm> [width height]");
+        std::process::exit(1);
+    };
+    Args {
+        level_dir,
+        leaderboard,
+        replay,
+        input,
+        renderer,
+        colors,
+        dither,
+        output,
+        record,
+        fov,
+        view_distance,
+        fog_start,
+        target_fps,
+    }
+}
+
+/// A setting given on the command line, which must be in the range the settings screen allows
+fn setting_arg(flag: &str, value: Option<&str>, min: f64, max: f64) -> Result<Option<f64>, String> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    match value.parse::<f64>() {
+        Ok(number) if (min..=max).contains(&number) => Ok(Some(number)),
+        _ => Err(format!("{flag} must be a number from {min} to {max}")),
+    }
+}
+
+/// Loads a replay file and finds the level it was recorded on
+fn find_replay(path: &str, levels: &[PathBuf]) -> Result<MenuChoice, String> {
+    let replay = replay::load(Path::new(path))?;
+    let index = levels
+        .iter()
+        .position(|level| {
+            let name = level.file_stem().unwrap_or_default().to_string_lossy();
+            fs::read_to_string(level)
+                .is_ok_and(|map| loader::level_id(&name, &map) == replay.level_id)
+        })
+        .ok_or(format!(
+            "no level in the folder matches the replay ({})",
+            replay.level_id
+        ))?;
+    Ok(MenuChoice::Replay(index, replay))
+}
 
 fn main() {
-    let level_dir = env::args().collect::<Vec<String>>()[1].clone();
-    let entries = fs::read_dir(level_dir).unwrap();
-    let levels: Vec<PathBuf> = entries.map(|e| e.unwrap().path()).collect();
+    if env::args().nth(1).as_deref() == Some("check") {
+        let paths: Vec<String> = env::args().skip(2).collect();
+        std::process::exit(check::run(&paths));
+    }
+    if env::args().nth(1).as_deref() == Some("bench") {
+        let args: Vec<String> = env::args().skip(2).collect();
+        std::process::exit(bench::run(&args));
+    }
+    if env::args().nth(1).as_deref() == Some("screenshot") {
+        let args: Vec<String> = env::args().skip(2).collect();
+        std::process::exit(screenshot::run(&args));
+    }
+    let args = parse_args();
+    let leader_board = match network::backend(args.leaderboard.as_deref()) {
+        Ok(backend) => backend,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    let input_backend = match input::backend(args.input.as_deref()) {
+        Ok(backend) => backend,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    let render_backend = match renderer::backend(args.renderer.as_deref()) {
+        Ok(backend) => backend,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    let palette = match color::depth(args.colors.as_deref()) {
+        Ok(depth) => color::Palette {
+            depth,
+            dither: args.dither,
+        },
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    if let Some(path) = &args.output {
+        match output::Sink::file(Path::new(path)) {
+            Ok(sink) => {
+                output::set_sink(sink);
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+    }
+    // found out now rather than after the first run
+    if let Some(path) = &args.record {
+        if let Err(e) = fs::File::create(path) {
+            eprintln!("{path}: {e}");
+            std::process::exit(1);
+        }
+    }
+    let pack = match pack::load(Path::new(&args.level_dir)) {
+        Ok(pack) => pack,
+        Err(e) => {
+            eprintln!("couldn't load level pack: {e}");
+            std::process::exit(1);
+        }
+    };
+    let levels: Vec<PathBuf> = pack.levels.iter().map(|level| level.path.clone()).collect();
+    // a replay from the command line is played before showing the menu
+    let mut pending_choice = match args.replay {
+        Some(path) => match find_replay(&path, &levels) {
+            Ok(choice) => Some(choice),
+            Err(e) => {
+                eprintln!("couldn't play replay: {e}");
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let mut profile = Profile::load();
+    // the command line wins over the saved settings, for this session
+    let mut overrides = Overrides::default();
+    let flags = [
+        (
+            "--fov",
+            &args.fov,
+            profile::MIN_FOV,
+            profile::MAX_FOV,
+            &mut overrides.fov,
+        ),
+        (
+            "--view-distance",
+            &args.view_distance,
+            profile::MIN_VIEW_DIST,
+            profile::MAX_VIEW_DIST,
+            &mut overrides.view_dist,
+        ),
+        (
+            "--fog-start",
+            &args.fog_start,
+            0.,
+            profile::MAX_FOG_START,
+            &mut overrides.fog_start,
+        ),
+        (
+            "--target-fps",
+            &args.target_fps,
+            0.,
+            profile::MAX_TARGET_FPS,
+            &mut overrides.target_fps,
+        ),
+    ];
+    for (flag, value, min, max, setting) in flags {
+        match setting_arg(flag, value.as_deref(), min, max) {
+            Ok(number) => *setting = number,
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+    }
     let (_stream, stream_handle) = OutputStream::try_default().expect("couldnt get sound handle!");
     crossterm::terminal::enable_raw_mode().unwrap();
+    if input_backend == input::Backend::Terminal {
+        input::use_terminal();
+    }
     let focused = Arc::new(Mutex::new(true));
     let focused_clone = Arc::clone(&focused);
 
     let _focus_thread = thread::spawn(move || loop {
         match read().expect("couldn't read event's") {
-            Event::FocusLost => *focused_clone.lock().unwrap() = false,
+            Event::FocusLost => {
+                *focused_clone.lock().unwrap() = false;
+                input::clear_keys();
+            }
             Event::FocusGained => *focused_clone.lock().unwrap() = true,
+            Event::Key(key) => input::handle_key_event(key),
             _ => (),
         }
     });
 
+    audio::set_volume(profile.settings.volume);
+    keymap::set_active(profile.settings.keymap);
+    let mut menu_state = MenuState::default();
     loop {
-        let chosen_level = screens::menu(levels.clone(), &stream_handle, focused.clone());
-        let map = loader::load(&levels[chosen_level]);
+        let choice = pending_choice.take().unwrap_or_else(|| {
+            screens::menu(
+                &pack,
+                &mut menu_state,
+                &mut profile,
+                &stream_handle,
+                leader_board.as_ref(),
+                focused.clone(),
+            )
+        });
+        let (chosen_level, playback) = match choice {
+            MenuChoice::Play(level) => (level, None),
+            MenuChoice::Replay(level, replay) => (level, Some(replay)),
+        };
+        // read again after every menu, the settings screen may have changed them
+        let settings = overrides.apply(&profile.settings);
+        let map = match loader::load(&levels[chosen_level]) {
+            Ok(map) => LevelMap {
+                light: Light {
+                    shadows: settings.shadows,
+                    fog_start: settings.fog_start,
+                    ..pack.levels[chosen_level].light
+                },
+                ..map
+            },
+            Err(e) => {
+                // the menu checks levels before playing them, this is for --replay
+                screens::game_over(&e.to_string(), focused.clone());
+                continue;
+            }
+        };
 
         loop {
+            // reloaded every attempt so a new personal best becomes the ghost right away
+            let best = replay::load(&replay::best_path(&map.id())).ok();
             let mut game = game::Game {
-                renderer: Screen::new(),
+                renderer: Screen::new(settings.output, palette),
+                backend: render_backend,
                 camera: camera::Camera {
-                    pos: Vec3 {
-                        x: 0.,
-                        y: 0.,
-                        z: 0.,
-                    },
-                    focus_length: 1.5, //2
-                    rotation: Vec3 {
-                        x: 1.75,
-                        y: 0.0,
-                        z: 0.0,
-                    },
-                    vel: Vec3 {
-                        x: 0.,
-                        y: 0.,
-                        z: 0.,
-                    },
+                    focus_length: settings.focus_length(),
+                    view_dist: settings.view_dist,
+                    ..Default::default()
                 },
+                recording: vec![],
+                cast_path: args.record.as_ref().map(PathBuf::from),
+                target_fps: Some(settings.target_fps).filter(|fps| *fps > 0.),
+            };
+            let mut input: Box<dyn InputSource> = match &playback {
+                Some(replay) => Box::new(ReplayInput::new(replay, focused.clone())),
+                None => Box::new(KeyboardInput::new(focused.clone())),
             };
-            match game.run(map.clone(), &stream_handle, focused.clone()) {
+            match game.run(
+                map.clone(),
+                &stream_handle,
+                focused.clone(),
+                input.as_mut(),
+                // race against the personal best, but not while watching a replay
+                best.as_ref()
+                    .filter(|_| playback.is_none())
+                    .and_then(Ghost::from_replay),
+            ) {
                 Ok(time) => {
-                    if screens::finish(time, &map.level_name, &map.map_string, focused.clone()) == 1
-                    {
+                    if let Some(replay) = &playback {
+                        let message = if replay::same_time(time, replay.time) {
+                            format!("Replay finished in {:.2}s", time)
+                        } else {
+                            format!("Replay desynced: {:.2}s, not {:.2}s", time, replay.time)
+                        };
+                        if screens::game_over(&message, focused.clone()) {
+                            continue;
+                        }
+                        break;
+                    }
+                    let _ = replay::save_run(&Replay {
+                        level_id: map.id(),
+                        time,
+                        ticks: game.recording.clone(),
+                    });
+                    let _ = Progress::load(&pack).complete(&pack.levels[chosen_level], time);
+                    // the menu opens on the level that was just unlocked
+                    if menu_state.campaign && chosen_level + 1 < pack.levels.len() {
+                        menu_state.selected = chosen_level + 1;
+                    }
+                    let best = profile.record(&map.id(), time);
+                    let finished = screens::finish(
+                        time,
+                        best,
+                        &mut profile.name,
+                        &map.level_name,
+                        &map.map_string,
+                        leader_board.as_ref(),
+                        focused.clone(),
+                    );
+                    // keeps the new personal best and the name typed on the finish screen
+                    let _ = profile.save();
+                    if finished == 1 {
                         continue;
                     }
                 }
@@ -91,6 +414,11 @@ fn main() {
                     }
                     "menu" => (),
                     "retry" => continue,
+                    "input_ended" => {
+                        if screens::game_over("The replay ended early!", focused.clone()) {
+                            continue;
+                        }
+                    }
                     _ => {
                         if screens::game_over("You failed!", focused.clone()) {
                             continue;
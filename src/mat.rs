@@ -1,6 +1,6 @@
 use std::ops::{Add, Div, Mul, Sub};
 
-use device_query::{DeviceQuery, Keycode};
+use crate::texture::Texture;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vec3 {
@@ -15,6 +15,11 @@ pub struct Tri {
     pub v1: Vec3,
     pub v2: Vec3,
     pub color: Vec3,
+    // 1 is opaque, lower values are blended over whatever is behind
+    pub alpha: f64,
+    // texture coordinates of v0, v1 and v2, in world units
+    pub uv: [(f64, f64); 3],
+    pub texture: Texture,
 }
 
 #[derive(Clone)]
@@ -51,6 +56,13 @@ impl Vec3 {
         ret.rotate_y(rotation.x)
     }
 
+    /// Undoes `rotate` with the same rotation
+    pub fn unrotate(self, rotation: Vec3) -> Vec3 {
+        let ret = self.rotate_y(-rotation.x);
+        let ret = ret.rotate_x(-rotation.y);
+        ret.rotate_z(-rotation.z)
+    }
+
     pub fn rotate_x(self, angle: f64) -> Vec3 {
         let y = self.y * angle.cos() - self.z * angle.sin();
         let z = self.y * angle.sin() + self.z * angle.cos();
@@ -79,6 +91,29 @@ impl Tri {
         a.cross(b).norm()
     }
 
+    /// Colour at `p`, a point on the tri, with the texture applied
+    pub fn color_at(self, p: Vec3) -> Vec3 {
+        if self.texture == Texture::Flat {
+            return self.color;
+        }
+        // barycentric coordinates of p
+        let e1 = self.v1 - self.v0;
+        let e2 = self.v2 - self.v0;
+        let d = p - self.v0;
+        let (d11, d12, d22) = (e1.dot(e1), e1.dot(e2), e2.dot(e2));
+        let denom = d11 * d22 - d12 * d12;
+        if denom == 0. {
+            return self.color;
+        }
+        let b1 = (d22 * d.dot(e1) - d12 * d.dot(e2)) / denom;
+        let b2 = (d11 * d.dot(e2) - d12 * d.dot(e1)) / denom;
+        let b0 = 1. - b1 - b2;
+        let [uv0, uv1, uv2] = self.uv;
+        let u = uv0.0 * b0 + uv1.0 * b1 + uv2.0 * b2;
+        let v = uv0.1 * b0 + uv1.1 * b1 + uv2.1 * b2;
+        self.color * self.texture.sample(u, v)
+    }
+
     pub fn hit_geo(self, ro: Vec3, rd: Vec3) -> (bool, f64) {
         let rd = rd.norm();
         let n = self.normal();
@@ -165,6 +200,9 @@ impl Mesh {
                     y: vertices[i].1,
                     z: vertices[i].2,
                 },
+                alpha: 1.,
+                uv: [(0., 0.); 3],
+                texture: Texture::Flat,
             };
             tris.push(tri);
             i += 4;
@@ -191,6 +229,33 @@ impl Mesh {
     pub fn mut_tris(&mut self) -> &mut Vec<Tri> {
         &mut self.tris
     }
+
+    pub fn with_alpha(mut self, alpha: f64) -> Self {
+        for tri in self.tris.iter_mut() {
+            tri.alpha = alpha;
+        }
+        self
+    }
+
+    /// Puts `texture` on every tri, projected straight onto the face so it isn't stretched
+    pub fn with_texture(mut self, texture: Texture) -> Self {
+        for tri in self.tris.iter_mut() {
+            let n = tri.normal();
+            let (nx, ny, nz) = (n.x.abs(), n.y.abs(), n.z.abs());
+            let project = |p: Vec3| {
+                if ny >= nx && ny >= nz {
+                    (p.x, p.z)
+                } else if nx >= nz {
+                    (p.z, p.y)
+                } else {
+                    (p.x, p.y)
+                }
+            };
+            tri.uv = [project(tri.v0), project(tri.v1), project(tri.v2)];
+            tri.texture = texture;
+        }
+        self
+    }
 }
 
 impl Add<Mesh> for Mesh {
@@ -389,12 +454,3 @@ pub fn check_collision(
 
     t
 }
-
-pub fn get_keys_conditional(check: bool) -> Vec<Keycode> {
-    let keys = device_query::DeviceState::new();
-    let mut keys = keys.get_keys();
-    if !check {
-        keys = vec![];
-    }
-    keys
-}
@@ -1,38 +1,166 @@
+use std::fs;
+use std::path::PathBuf;
+
 use serde_json::{json, Value};
 use sha3::{digest::Update, Digest, Sha3_256};
 
-const SALT: &'static str = env!("SALT");
+use crate::paths;
+
+const SERVER: &str = "http://danielsson.pythonanywhere.com";
 
-fn hash(string: String) -> String {
-    let mut hasher = Sha3_256::new();
-    Update::update(&mut hasher, &(string + SALT).into_bytes());
-    let result = hasher.finalize();
+// Only official builds get the salt, source builds fall back to the local leaderboard.
+const SALT: Option<&str> = option_env!("SALT");
 
-    let result = &result[..];
-    let result = hex::encode(result);
-    return result;
+/// Somewhere to store and fetch results for a level.
+/// Leaderboards are json arrays of `{"name": <string>, "time": <seconds>}` objects.
+pub trait LeaderboardBackend {
+    fn get_leader_board(&self, level_id: &str) -> Value;
+    fn log_result(&self, level_id: &str, name: &str, time: f64) -> Result<(), String>;
+
+    /// Names and times of a level, fastest first
+    fn results(&self, level_id: &str) -> Vec<(String, f64)> {
+        let leader_board = self.get_leader_board(level_id);
+        let mut results: Vec<(String, f64)> = leader_board
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                Some((
+                    entry.get("name")?.as_str()?.to_string(),
+                    entry.get("time")?.as_f64()?,
+                ))
+            })
+            .collect();
+        results.sort_by(|a, b| a.1.total_cmp(&b.1));
+        results
+    }
 }
 
-pub fn get_leader_board(level_id: &str) -> Value {
-    // get leaderboard
-    let leader_board = match reqwest::blocking::get(&format!(
-        "http://danielsson.pythonanywhere.com/get_result/{level_id}"
-    )) {
-        Ok(resp) => resp.text().unwrap(),
-        Err(resp) => panic!("Err: {}", resp),
+// keeps the entries with a name and a time, anything that isn't a list is an empty one
+fn valid_entries(leader_board: Value) -> Value {
+    let Value::Array(entries) = leader_board else {
+        return json!([]);
     };
-    let mut leader_board: Value = serde_json::from_str(&leader_board).unwrap_or(json!([]));
-    leader_board
-}
-pub fn log_result(
-    id: &str,
-    name: &str,
-    time: f64,
-) -> Result<reqwest::blocking::Response, reqwest::Error> {
-    let string = id.to_owned() + &time.to_string();
-    let h = hash(string);
-    let brr = reqwest::blocking::get(&format!(
-        "http://danielsson.pythonanywhere.com/log_result/{id}/{name}/{time}/{h}"
-    ));
-    brr
+    entries
+        .into_iter()
+        .filter(|entry| {
+            entry.get("name").is_some_and(Value::is_string)
+                && entry.get("time").is_some_and(Value::is_number)
+        })
+        .collect()
+}
+
+/// The online leaderboard at danielsson.pythonanywhere.com
+pub struct HttpLeaderboard {
+    salt: &'static str,
+}
+
+/// Leaderboard stored as one json file per level in the data dir
+pub struct LocalLeaderboard {
+    dir: PathBuf,
+}
+
+/// Picks the leaderboard by name ("online" or "local").
+/// Without a name the online one is used if this build has the salt for it.
+pub fn backend(kind: Option<&str>) -> Result<Box<dyn LeaderboardBackend>, String> {
+    match (kind, SALT) {
+        (Some("online") | None, Some(salt)) => Ok(Box::new(HttpLeaderboard { salt })),
+        (Some("online"), None) => {
+            Err("this build has no salt, the online leaderboard is not available".to_string())
+        }
+        (Some("local") | None, _) => Ok(Box::new(LocalLeaderboard::new(
+            paths::data_dir().join("leaderboards"),
+        ))),
+        (Some(kind), _) => Err(format!("unknown leaderboard '{kind}', use online or local")),
+    }
+}
+
+impl HttpLeaderboard {
+    fn hash(&self, string: String) -> String {
+        let mut hasher = Sha3_256::new();
+        Update::update(&mut hasher, &(string + self.salt).into_bytes());
+        let result = hasher.finalize();
+
+        hex::encode(&result[..])
+    }
+}
+
+impl LeaderboardBackend for HttpLeaderboard {
+    fn get_leader_board(&self, level_id: &str) -> Value {
+        // show an empty leaderboard when offline instead of crashing
+        match reqwest::blocking::get(format!("{SERVER}/get_result/{level_id}"))
+            .and_then(|resp| resp.text())
+        {
+            Ok(text) => valid_entries(serde_json::from_str(&text).unwrap_or(json!([]))),
+            Err(_) => json!([]),
+        }
+    }
+
+    fn log_result(&self, level_id: &str, name: &str, time: f64) -> Result<(), String> {
+        let string = level_id.to_owned() + &time.to_string();
+        let h = self.hash(string);
+        reqwest::blocking::get(format!("{SERVER}/log_result/{level_id}/{name}/{time}/{h}"))
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl LocalLeaderboard {
+    pub fn new(dir: PathBuf) -> Self {
+        LocalLeaderboard { dir }
+    }
+
+    fn path(&self, level_id: &str) -> PathBuf {
+        self.dir.join(format!("{level_id}.json"))
+    }
+}
+
+impl LeaderboardBackend for LocalLeaderboard {
+    fn get_leader_board(&self, level_id: &str) -> Value {
+        // a broken file is no reason to stop playing offline, it is replaced on the next finish
+        fs::read_to_string(self.path(level_id))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .map_or(json!([]), valid_entries)
+    }
+
+    fn log_result(&self, level_id: &str, name: &str, time: f64) -> Result<(), String> {
+        let mut leader_board = self.get_leader_board(level_id);
+        leader_board
+            .as_array_mut()
+            .ok_or("leaderboard file is not a list")?
+            .push(json!({"name": name, "time": time}));
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        fs::write(self.path(level_id), leader_board.to_string()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broken_local_files_read_as_empty_or_valid_entries() {
+        let dir =
+            std::env::temp_dir().join(format!("termtrack-leaderboard-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let local = LocalLeaderboard::new(dir.clone());
+        fs::write(local.path("object"), r#"{"name": "a", "time": 1}"#).unwrap();
+        fs::write(
+            local.path("mixed"),
+            r#"[{"name": "b", "time": 3}, {"name": "c"}, 5, {"name": 1, "time": 2}, {"name": "d", "time": 2.5}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(local.get_leader_board("object"), json!([]));
+        assert_eq!(local.get_leader_board("missing"), json!([]));
+        assert_eq!(
+            local.results("mixed"),
+            [("d".to_string(), 2.5), ("b".to_string(), 3.)]
+        );
+        // a broken file is replaced by the new result
+        local.log_result("object", "e", 4.).unwrap();
+        assert_eq!(local.results("object"), [("e".to_string(), 4.)]);
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,169 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::cast::Recorder;
+
+/// Where the game draws to. Everything on screen goes through `out!` and `outln!`,
+/// which write to the current sink instead of stdout.
+pub enum Sink {
+    Stdout,
+    File(File),
+    Memory(Memory),
+    // everything written also goes to the recording
+    Recorded(Box<Sink>, Recorder),
+}
+
+impl Sink {
+    /// Writes to `path`, a file, a FIFO or another terminal
+    pub fn file(path: &Path) -> Result<Sink, String> {
+        let file = File::create(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        Ok(Sink::File(file))
+    }
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Sink::Stdout => io::stdout().write(buf),
+            Sink::File(file) => file.write(buf),
+            Sink::Memory(memory) => {
+                memory.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            Sink::Recorded(sink, recorder) => {
+                let written = sink.write(buf)?;
+                recorder.write(&buf[..written]);
+                Ok(written)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Stdout => io::stdout().flush(),
+            Sink::File(file) => file.flush(),
+            Sink::Memory(_) => Ok(()),
+            Sink::Recorded(sink, recorder) => {
+                recorder.flush();
+                sink.flush()
+            }
+        }
+    }
+}
+
+/// Output kept in memory, clones share what was written
+#[derive(Clone, Debug, Default)]
+pub struct Memory(Arc<Mutex<Vec<u8>>>);
+
+impl Memory {
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+static SINK: Mutex<Sink> = Mutex::new(Sink::Stdout);
+
+/// Sends the output to `sink` from now on, returns the one used until now
+pub fn set_sink(sink: Sink) -> Sink {
+    let mut previous = SINK.lock().unwrap();
+    let _ = previous.flush();
+    std::mem::replace(&mut previous, sink)
+}
+
+/// Records everything written from now on as well, until `stop_recording`
+pub fn start_recording(recorder: Recorder) {
+    let mut sink = SINK.lock().unwrap();
+    let inner = std::mem::replace(&mut *sink, Sink::Stdout);
+    *sink = Sink::Recorded(Box::new(inner), recorder);
+}
+
+pub fn stop_recording() {
+    let mut sink = SINK.lock().unwrap();
+    if !matches!(*sink, Sink::Recorded(..)) {
+        return;
+    }
+    if let Sink::Recorded(inner, mut recorder) = std::mem::replace(&mut *sink, Sink::Stdout) {
+        recorder.flush();
+        *sink = *inner;
+    }
+}
+
+// unlike print! a closed pipe or a full disk doesn't panic, the frame is just lost.
+// Written in one go so a recording gets one event per call, not one per argument.
+pub fn write(args: fmt::Arguments) {
+    let _ = SINK.lock().unwrap().write_all(fmt::format(args).as_bytes());
+}
+
+/// Pushes out what stdout holds back, done at the end of every frame
+pub fn flush() {
+    let _ = SINK.lock().unwrap().flush();
+}
+
+/// `print!` to the current sink
+macro_rules! out {
+    ($($arg:tt)*) => {
+        $crate::output::write(format_args!($($arg)*))
+    };
+}
+
+/// `println!` to the current sink
+macro_rules! outln {
+    () => {
+        $crate::output::write(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {{
+        $crate::output::write(format_args!($($arg)*));
+        $crate::output::write(format_args!("\n"));
+    }};
+}
+
+pub(crate) use {out, outln};
+
+// held while a test has the sink swapped, tests run in parallel and the sink is global
+#[cfg(test)]
+static CAPTURE: Mutex<()> = Mutex::new(());
+
+/// What `draw` writes, kept from the output. Tests that draw go through this so they
+/// don't write into each other's sinks.
+#[cfg(test)]
+pub fn capture(draw: impl FnOnce()) -> String {
+    // a failed test poisons the lock, the sink is put back either way
+    let _guard = CAPTURE.lock().unwrap_or_else(|e| e.into_inner());
+    let memory = Memory::default();
+    let previous = set_sink(Sink::Memory(memory.clone()));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(draw));
+    set_sink(previous);
+    if let Err(panic) = result {
+        std::panic::resume_unwind(panic);
+    }
+    memory.contents()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Palette;
+    use crate::mat::Vec3;
+    use crate::renderer::{OutputMode, Screen};
+
+    #[test]
+    fn captures_the_map_in_memory() {
+        let drawn = capture(|| {
+            let screen = Screen::with_size(40, 20, OutputMode::Color, Palette::TRUE_COLOR);
+            let position = Vec3 {
+                x: 25.,
+                y: 0.,
+                z: 15.,
+            };
+            screen.render_map("-----\n-S.G-\n-----", position, 10., 10.);
+            outln!("done");
+        });
+        assert!(drawn.contains("| -----   |"));
+        // the player is highlighted on the second row
+        assert!(drawn.contains("-S\x1b[48;2;50;255;50m.\x1b[48;2;000;000;000mG-"));
+        assert!(drawn.ends_with("done\n"));
+    }
+}
@@ -0,0 +1,197 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::light::Light;
+
+/// Optional file in a level folder describing the pack
+pub const MANIFEST: &str = "pack.json";
+
+/// A level of a pack and what the manifest says about it
+#[derive(Clone)]
+pub struct PackLevel {
+    pub path: PathBuf,
+    // shown in the menu instead of the file name
+    pub title: String,
+    pub difficulty: Option<String>,
+    // target time in seconds
+    pub par: Option<f64>,
+    pub light: Light,
+}
+
+/// A folder of levels, in the order they are meant to be played
+#[derive(Clone)]
+pub struct Pack {
+    // folder the levels were loaded from
+    pub dir: PathBuf,
+    pub name: String,
+    pub author: Option<String>,
+    pub levels: Vec<PackLevel>,
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Reads the pack in `dir`. Without a manifest every file is a level, sorted by name.
+pub fn load(dir: &Path) -> Result<Pack, String> {
+    let manifest = dir.join(MANIFEST);
+    if manifest.exists() {
+        let text = fs::read_to_string(&manifest).map_err(|e| format!("{MANIFEST}: {e}"))?;
+        let value: Value = serde_json::from_str(&text).map_err(|e| format!("{MANIFEST}: {e}"))?;
+        let pack = from_json(dir, &value).map_err(|e| format!("{MANIFEST}: {e}"))?;
+        if pack.levels.is_empty() {
+            return Err(format!("{MANIFEST} lists no levels"));
+        }
+        return Ok(pack);
+    }
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("{}: {e}", dir.display()))?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+    if paths.is_empty() {
+        return Err(format!("no levels in {}", dir.display()));
+    }
+    Ok(Pack {
+        dir: dir.to_path_buf(),
+        name: file_stem(dir),
+        author: None,
+        levels: paths
+            .into_iter()
+            .map(|path| PackLevel {
+                title: file_stem(&path),
+                path,
+                difficulty: None,
+                par: None,
+                light: Light::default(),
+            })
+            .collect(),
+    })
+}
+
+// {"name": .., "author": .., "levels": ["file", {"file": .., "title": .., "difficulty": .., "par": .., "light": ..}]},
+// see `Light::from_json` for the light
+fn from_json(dir: &Path, value: &Value) -> Result<Pack, String> {
+    let name = match value.get("name") {
+        Some(name) => name.as_str().ok_or("name must be a string")?.to_string(),
+        None => file_stem(dir),
+    };
+    let author = match value.get("author") {
+        Some(author) => Some(
+            author
+                .as_str()
+                .ok_or("author must be a string")?
+                .to_string(),
+        ),
+        None => None,
+    };
+    let mut levels = vec![];
+    for entry in value
+        .get("levels")
+        .and_then(Value::as_array)
+        .ok_or("levels must be a list")?
+    {
+        let file = match entry {
+            Value::String(file) => file.as_str(),
+            entry => entry
+                .get("file")
+                .and_then(Value::as_str)
+                .ok_or("every level needs a file")?,
+        };
+        let path = dir.join(file);
+        if !path.is_file() {
+            return Err(format!("level {file} doesn't exist"));
+        }
+        let title = match entry.get("title") {
+            Some(title) => title
+                .as_str()
+                .ok_or(format!("title of {file} must be a string"))?
+                .to_string(),
+            None => file_stem(&path),
+        };
+        let difficulty = match entry.get("difficulty") {
+            Some(difficulty) => Some(
+                difficulty
+                    .as_str()
+                    .ok_or(format!("difficulty of {file} must be a string"))?
+                    .to_string(),
+            ),
+            None => None,
+        };
+        let par = match entry.get("par") {
+            Some(par) => Some(
+                par.as_f64()
+                    .ok_or(format!("par of {file} must be a number of seconds"))?,
+            ),
+            None => None,
+        };
+        let light = match entry.get("light") {
+            Some(light) => Light::from_json(light).map_err(|e| format!("{file}: {e}"))?,
+            None => Light::default(),
+        };
+        levels.push(PackLevel {
+            path,
+            title,
+            difficulty,
+            par,
+            light,
+        });
+    }
+    Ok(Pack {
+        dir: dir.to_path_buf(),
+        name,
+        author,
+        levels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn falls_back_to_sorted_files() {
+        let pack = load(Path::new("level_pack_0")).unwrap();
+        let titles: Vec<&str> = pack.levels.iter().map(|l| l.title.as_str()).collect();
+        assert_eq!(titles, ["level_0", "level_1", "level_2", "level_3"]);
+    }
+
+    #[test]
+    fn manifest_sets_order_and_metadata() {
+        let manifest = json!({
+            "name": "Test pack",
+            "levels": [
+                {"file": "level_2.txt", "title": "Spikes", "difficulty": "hard", "par": 30,
+                 "light": {"ambient": 0.3}},
+                "level_0.txt",
+            ],
+        });
+        let pack = from_json(Path::new("level_pack_0"), &manifest).unwrap();
+        assert_eq!(pack.name, "Test pack");
+        assert_eq!(pack.levels[0].title, "Spikes");
+        assert_eq!(pack.levels[0].difficulty.as_deref(), Some("hard"));
+        assert_eq!(pack.levels[0].par, Some(30.));
+        assert_eq!(pack.levels[1].title, "level_0");
+        assert_eq!(pack.levels[1].par, None);
+        assert_eq!(pack.levels[0].light.ambient, 0.3);
+        assert_eq!(pack.levels[1].light, Light::default());
+    }
+
+    #[test]
+    fn manifest_levels_must_exist() {
+        let manifest = json!({"levels": ["missing.txt"]});
+        assert!(from_json(Path::new("level_pack_0"), &manifest).is_err());
+    }
+
+    #[test]
+    fn manifest_light_must_be_valid() {
+        let manifest = json!({"levels": [{"file": "level_0.txt", "light": {"fog": 1}}]});
+        assert!(from_json(Path::new("level_pack_0"), &manifest).is_err());
+    }
+}
@@ -0,0 +1,32 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Directory for files the game writes on its own (leaderboards, replays, ...).
+/// Follows XDG on linux and uses %APPDATA% on windows, falls back to `./data`.
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("XDG_DATA_HOME") {
+        return PathBuf::from(dir).join("termtrack");
+    }
+    if let Some(dir) = env::var_os("APPDATA") {
+        return PathBuf::from(dir).join("termtrack");
+    }
+    if let Some(home) = env::var_os("HOME") {
+        return PathBuf::from(home).join(".local/share/termtrack");
+    }
+    PathBuf::from("./data")
+}
+
+/// Directory for the player's profile and settings.
+/// Looked up like `data_dir`, but from XDG_CONFIG_HOME and `~/.config`.
+pub fn config_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        return PathBuf::from(dir).join("termtrack");
+    }
+    if let Some(dir) = env::var_os("APPDATA") {
+        return PathBuf::from(dir).join("termtrack");
+    }
+    if let Some(home) = env::var_os("HOME") {
+        return PathBuf::from(home).join(".config/termtrack");
+    }
+    PathBuf::from("./config")
+}
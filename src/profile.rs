@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::{json, Map, Value};
+
+use crate::keymap::Keymap;
+use crate::paths;
+use crate::renderer::OutputMode;
+
+// field of view of the old fixed focus length of 1.5
+const DEFAULT_FOV: f64 = 67.38;
+pub const MIN_FOV: f64 = 40.;
+pub const MAX_FOV: f64 = 120.;
+// in world units, a block is 10 wide
+const DEFAULT_VIEW_DIST: f64 = 45.;
+pub const MIN_VIEW_DIST: f64 = 20.;
+pub const MAX_VIEW_DIST: f64 = 200.;
+pub const MAX_FOG_START: f64 = 0.9;
+pub const MAX_TARGET_FPS: f64 = 120.;
+
+/// Options from the settings screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    // 0 is muted, 1 is full volume
+    pub volume: f64,
+    // field of view in degrees across the shorter side of the screen, which is the
+    // height on all but very narrow terminals
+    pub fov: f64,
+    pub view_dist: f64,
+    // fraction of the view distance without fog
+    pub fog_start: f64,
+    pub output: OutputMode,
+    // shadows cast by the level, a second ray per pixel so off unless asked for
+    pub shadows: bool,
+    // frame rate the resolution is lowered to keep, 0 always renders the whole terminal
+    pub target_fps: f64,
+    pub keymap: Keymap,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            volume: 1.,
+            fov: DEFAULT_FOV,
+            view_dist: DEFAULT_VIEW_DIST,
+            fog_start: 0.,
+            output: OutputMode::Color,
+            shadows: false,
+            target_fps: 0.,
+            keymap: Keymap::DEFAULT,
+        }
+    }
+}
+
+impl Settings {
+    /// Camera focus length giving the field of view
+    pub fn focus_length(&self) -> f64 {
+        1. / (self.fov.to_radians() / 2.).tan()
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "volume": self.volume,
+            "fov": self.fov,
+            "view_distance": self.view_dist,
+            "fog_start": self.fog_start,
+            "render_mode": self.output.name(),
+            "shadows": self.shadows,
+            "target_fps": self.target_fps,
+            "keys": self.keymap.to_json(),
+        })
+    }
+
+    // missing or broken values keep their default
+    fn from_json(value: &Value) -> Settings {
+        let default = Settings::default();
+        Settings {
+            volume: value
+                .get("volume")
+                .and_then(Value::as_f64)
+                .map_or(default.volume, |v| v.clamp(0., 1.)),
+            fov: value
+                .get("fov")
+                .and_then(Value::as_f64)
+                .map_or(default.fov, |fov| fov.clamp(MIN_FOV, MAX_FOV)),
+            view_dist: value
+                .get("view_distance")
+                .and_then(Value::as_f64)
+                .map_or(default.view_dist, |d| d.clamp(MIN_VIEW_DIST, MAX_VIEW_DIST)),
+            fog_start: value
+                .get("fog_start")
+                .and_then(Value::as_f64)
+                .map_or(default.fog_start, |f| f.clamp(0., MAX_FOG_START)),
+            output: value
+                .get("render_mode")
+                .and_then(Value::as_str)
+                .and_then(OutputMode::from_name)
+                .unwrap_or(default.output),
+            shadows: value
+                .get("shadows")
+                .and_then(Value::as_bool)
+                .unwrap_or(default.shadows),
+            target_fps: value
+                .get("target_fps")
+                .and_then(Value::as_f64)
+                .map_or(default.target_fps, |fps| fps.clamp(0., MAX_TARGET_FPS)),
+            keymap: Keymap::from_json(value.get("keys").unwrap_or(&Value::Null)),
+        }
+    }
+}
+
+/// Settings given on the command line. They win over the saved ones for the session but
+/// are never saved, `Profile::settings` keeps what the player chose.
+#[derive(Clone, Debug, Default)]
+pub struct Overrides {
+    pub fov: Option<f64>,
+    pub view_dist: Option<f64>,
+    pub fog_start: Option<f64>,
+    pub target_fps: Option<f64>,
+}
+
+impl Overrides {
+    /// The settings to play with
+    pub fn apply(&self, saved: &Settings) -> Settings {
+        Settings {
+            fov: self.fov.unwrap_or(saved.fov),
+            view_dist: self.view_dist.unwrap_or(saved.view_dist),
+            fog_start: self.fog_start.unwrap_or(saved.fog_start),
+            target_fps: self.target_fps.unwrap_or(saved.target_fps),
+            ..saved.clone()
+        }
+    }
+}
+
+/// The player: name for leaderboards, settings and personal bests by level id.
+/// Kept as `profile.json` in the config dir.
+pub struct Profile {
+    path: PathBuf,
+    pub name: String,
+    pub settings: Settings,
+    pub personal_bests: HashMap<String, f64>,
+}
+
+impl Profile {
+    /// Loads the profile, a new one if there is none or it can't be read
+    pub fn load() -> Profile {
+        let path = paths::config_dir().join("profile.json");
+        let value = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+            .unwrap_or(Value::Null);
+        Profile {
+            name: value
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            settings: Settings::from_json(value.get("settings").unwrap_or(&Value::Null)),
+            personal_bests: value
+                .get("personal_bests")
+                .and_then(Value::as_object)
+                .map(|bests| {
+                    bests
+                        .iter()
+                        .filter_map(|(id, time)| Some((id.clone(), time.as_f64()?)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            path,
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let bests: Map<String, Value> = self
+            .personal_bests
+            .iter()
+            .map(|(id, time)| (id.clone(), json!(time)))
+            .collect();
+        let value = json!({
+            "name": self.name,
+            "settings": self.settings.to_json(),
+            "personal_bests": bests,
+        });
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let text = serde_json::to_string_pretty(&value).map_err(|e| e.to_string())?;
+        fs::write(&self.path, text).map_err(|e| e.to_string())
+    }
+
+    /// Keeps `time` if it beats the personal best on the level, returns the best before it
+    pub fn record(&mut self, level_id: &str, time: f64) -> Option<f64> {
+        let previous = self.personal_bests.get(level_id).copied();
+        if previous.is_none_or(|best| time < best) {
+            self.personal_bests.insert(level_id.to_string(), time);
+        }
+        previous
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_fov_keeps_old_focus_length() {
+        let settings = Settings::default();
+        assert!((settings.focus_length() - 1.5).abs() < 0.001);
+        // and the old render distance of 30 ray lengths
+        assert!((settings.view_dist / settings.focus_length() - 30.).abs() < 0.1);
+    }
+
+    #[test]
+    fn settings_survive_json() {
+        let settings = Settings {
+            volume: 0.3,
+            fov: 90.,
+            view_dist: 120.,
+            fog_start: 0.5,
+            output: OutputMode::HalfBlock,
+            shadows: true,
+            target_fps: 30.,
+            keymap: Keymap::DEFAULT,
+        };
+        assert_eq!(Settings::from_json(&settings.to_json()), settings);
+        // out of range values are clamped, missing ones are defaults
+        let loaded = Settings::from_json(&json!({"fov": 500, "view_distance": 1}));
+        assert_eq!(loaded.fov, MAX_FOV);
+        assert_eq!(loaded.view_dist, MIN_VIEW_DIST);
+        assert_eq!(loaded.volume, 1.);
+        assert_eq!(loaded.target_fps, 0.);
+        assert!(!loaded.shadows);
+    }
+
+    #[test]
+    fn overrides_leave_saved_settings_alone() {
+        let saved = Settings {
+            volume: 0.5,
+            ..Settings::default()
+        };
+        let overrides = Overrides {
+            fov: Some(100.),
+            target_fps: Some(30.),
+            ..Overrides::default()
+        };
+        let session = overrides.apply(&saved);
+        assert_eq!(session.fov, 100.);
+        assert_eq!(session.target_fps, 30.);
+        assert_eq!(saved.target_fps, 0.);
+        assert_eq!(session.volume, 0.5);
+        assert_eq!(session.view_dist, saved.view_dist);
+        assert_eq!(saved.fov, DEFAULT_FOV);
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::{json, Map, Value};
+
+use crate::pack::{Pack, PackLevel};
+use crate::paths;
+
+/// How far the player has come with a level in campaign mode
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LevelState {
+    Locked,
+    Unlocked,
+    // best time
+    Completed(f64),
+}
+
+/// Finished levels of a pack and the best time on each, saved in the data dir
+pub struct Progress {
+    path: PathBuf,
+    best: HashMap<String, f64>,
+}
+
+// levels are remembered by file name, so fixing a typo in a level keeps the progress
+fn key(level: &PackLevel) -> String {
+    level
+        .path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn safe_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// packs are remembered by their folder, so renaming one in its manifest keeps the
+// progress and two packs with the same name don't share it. The folder name is only
+// there to find the file, the hash of the whole path tells folders apart.
+fn file_name(pack: &Pack) -> String {
+    let dir = fs::canonicalize(&pack.dir).unwrap_or_else(|_| pack.dir.clone());
+    let folder = dir
+        .file_name()
+        .map(|name| safe_name(&name.to_string_lossy()))
+        .unwrap_or_default();
+    let hash = crc32fast::hash(dir.to_string_lossy().as_bytes());
+    format!("{folder}-{hash:08x}.json")
+}
+
+impl Progress {
+    /// Progress of `pack`, nothing finished if there is no save yet
+    pub fn load(pack: &Pack) -> Progress {
+        let path = paths::data_dir().join("progress").join(file_name(pack));
+        let best = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+            .and_then(|value| {
+                let levels = value.get("levels")?.as_object()?;
+                Some(
+                    levels
+                        .iter()
+                        .filter_map(|(name, time)| Some((name.clone(), time.as_f64()?)))
+                        .collect(),
+                )
+            })
+            .unwrap_or_default();
+        Progress { path, best }
+    }
+
+    /// A level is unlocked once the one before it is finished, the first always is
+    pub fn state(&self, pack: &Pack, index: usize) -> LevelState {
+        if let Some(time) = self.best.get(&key(&pack.levels[index])) {
+            return LevelState::Completed(*time);
+        }
+        let previous_done = index
+            .checked_sub(1)
+            .is_none_or(|prev| self.best.contains_key(&key(&pack.levels[prev])));
+        if previous_done {
+            LevelState::Unlocked
+        } else {
+            LevelState::Locked
+        }
+    }
+
+    /// Remembers a finished run if it's the first or the fastest, and saves
+    pub fn complete(&mut self, level: &PackLevel, time: f64) -> Result<(), String> {
+        let best = self.best.entry(key(level)).or_insert(time);
+        *best = best.min(time);
+        let levels: Map<String, Value> = self
+            .best
+            .iter()
+            .map(|(name, time)| (name.clone(), json!(time)))
+            .collect();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(&self.path, json!({ "levels": levels }).to_string()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn finishing_a_level_unlocks_the_next() {
+        let pack = crate::pack::load(Path::new("level_pack_0")).unwrap();
+        let progress = Progress {
+            path: PathBuf::new(),
+            best: HashMap::from([("level_0".to_string(), 12.5)]),
+        };
+        assert_eq!(progress.state(&pack, 0), LevelState::Completed(12.5));
+        assert_eq!(progress.state(&pack, 1), LevelState::Unlocked);
+        assert_eq!(progress.state(&pack, 2), LevelState::Locked);
+    }
+
+    #[test]
+    fn saves_are_kept_per_folder() {
+        let pack = crate::pack::load(Path::new("level_pack_0")).unwrap();
+        let renamed = Pack {
+            name: "Another name".to_string(),
+            ..pack.clone()
+        };
+        assert_eq!(file_name(&pack), file_name(&renamed));
+        assert!(file_name(&pack).starts_with("level_pack_0-"));
+        // the same folder given another way
+        let relative = Pack {
+            dir: PathBuf::from("./level_pack_0"),
+            ..pack.clone()
+        };
+        assert_eq!(file_name(&pack), file_name(&relative));
+        let same_name = Pack {
+            dir: PathBuf::from("src"),
+            ..pack
+        };
+        assert_ne!(file_name(&renamed), file_name(&same_name));
+    }
+}
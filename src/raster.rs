@@ -0,0 +1,201 @@
+use rayon::prelude::*;
+
+use crate::bvh::{Bvh, RayHit};
+use crate::camera::Camera;
+use crate::light::Light;
+use crate::mat::{Tri, Vec3};
+use crate::renderer::{pixel_color, Renderer, Screen};
+
+// closest a corner may get to the camera before the triangle is cut, in camera space
+const NEAR: f64 = 0.01;
+// rows one thread fills at a time
+const BAND: usize = 4;
+
+// a triangle on the screen, every corner as (pixel x, pixel y, 1 / distance)
+struct Projected {
+    tri: Tri,
+    corners: [(f64, f64, f64); 3],
+    // pixel bounds, min x, max x, min y, max y
+    bounds: (f64, f64, f64, f64),
+}
+
+/// Projects every triangle onto the screen and fills it in, keeping the closest by depth.
+/// Gives the same picture as the ray caster, distances are measured the same way.
+pub struct Rasterizer;
+
+impl Renderer for Rasterizer {
+    fn render(
+        &self,
+        screen: &Screen,
+        camera: &Camera,
+        level: &Bvh,
+        dynamic: &[Tri],
+        light: &Light,
+    ) -> Vec<Vec<Vec3>> {
+        let projected: Vec<Projected> = level
+            .tris()
+            .par_iter()
+            .chain(dynamic.par_iter())
+            .flat_map_iter(|tri| project(screen, camera, tri))
+            .collect();
+
+        let mut buffer = vec![
+            vec![
+                Vec3 {
+                    x: 0.,
+                    y: 0.,
+                    z: 0.
+                };
+                screen.w
+            ];
+            screen.rows()
+        ];
+        buffer
+            .par_chunks_mut(BAND)
+            .enumerate()
+            .for_each(|(band, rows)| {
+                let top = band * BAND;
+                let mut hits = vec![RayHit::new(camera.max_ray_dist()); rows.len() * screen.w];
+                for p in &projected {
+                    fill(p, top, rows.len(), screen.w, camera, &mut hits);
+                }
+                for (dy, row) in rows.iter_mut().enumerate() {
+                    for (x, pixel) in row.iter_mut().enumerate() {
+                        let ray_dir = screen.pixel_ray(camera, x, top + dy);
+                        *pixel =
+                            pixel_color(&hits[dy * screen.w + x], camera, ray_dir, light, level);
+                    }
+                }
+            });
+        buffer
+    }
+}
+
+// the triangle in screen space, cut into one or two where it crosses the near plane
+fn project(screen: &Screen, camera: &Camera, tri: &Tri) -> Vec<Projected> {
+    let corners = [tri.v0, tri.v1, tri.v2].map(|v| (v - camera.pos).unrotate(camera.rotation));
+    let far = camera.view_dist;
+    if corners.iter().all(|c| c.z < NEAR) || corners.iter().all(|c| c.z > far) {
+        return vec![];
+    }
+
+    // Sutherland–Hodgman against the near plane
+    let mut polygon = Vec::with_capacity(4);
+    for i in 0..3 {
+        let (a, b) = (corners[i], corners[(i + 1) % 3]);
+        if a.z >= NEAR {
+            polygon.push(a);
+        }
+        if (a.z >= NEAR) != (b.z >= NEAR) {
+            polygon.push(a + (b - a) * ((NEAR - a.z) / (b.z - a.z)));
+        }
+    }
+
+    let min_dim = screen.w.min(screen.h * 2) as f64 / 2.;
+    // inverse of `Screen::pixel_ray`
+    let to_screen = |c: Vec3| {
+        let scale = camera.focus_length * min_dim / c.z;
+        (
+            c.x * scale + screen.w as f64 / 2.,
+            (c.y * scale + screen.h as f64 / 2.) / 2. * screen.mode.rows_per_cell() as f64,
+            1. / c.z,
+        )
+    };
+    let points: Vec<(f64, f64, f64)> = polygon.into_iter().map(to_screen).collect();
+    (1..points.len().saturating_sub(1))
+        .map(|i| [points[0], points[i], points[i + 1]])
+        .filter_map(|corners| {
+            let xs = corners.map(|c| c.0);
+            let ys = corners.map(|c| c.1);
+            let bounds = (
+                xs.iter().copied().fold(f64::MAX, f64::min),
+                xs.iter().copied().fold(f64::MIN, f64::max),
+                ys.iter().copied().fold(f64::MAX, f64::min),
+                ys.iter().copied().fold(f64::MIN, f64::max),
+            );
+            let on_screen = bounds.1 >= 0.
+                && bounds.0 < screen.w as f64
+                && bounds.3 >= 0.
+                && bounds.2 < screen.rows() as f64;
+            on_screen.then_some(Projected {
+                tri: *tri,
+                corners,
+                bounds,
+            })
+        })
+        .collect()
+}
+
+// puts the pixels of `p` within the rows top..top + height into `hits`
+fn fill(
+    p: &Projected,
+    top: usize,
+    height: usize,
+    width: usize,
+    camera: &Camera,
+    hits: &mut [RayHit],
+) {
+    let (min_x, max_x, min_y, max_y) = p.bounds;
+    let y0 = (min_y.ceil().max(top as f64)) as usize;
+    let y1 = (max_y.floor() + 1.).min((top + height) as f64);
+    let x0 = min_x.ceil().max(0.) as usize;
+    let x1 = (max_x.floor() + 1.).min(width as f64);
+    if y1 <= y0 as f64 || x1 <= x0 as f64 {
+        return;
+    }
+    let [(ax, ay, az), (bx, by, bz), (cx, cy, cz)] = p.corners;
+    let area = (bx - ax) * (cy - ay) - (by - ay) * (cx - ax);
+    if area.abs() < 1e-9 {
+        return;
+    }
+    for y in y0..y1 as usize {
+        let py = y as f64;
+        for x in x0..x1 as usize {
+            let px = x as f64;
+            // barycentric weights, all positive inside whichever way the corners go round
+            let wa = ((bx - px) * (cy - py) - (by - py) * (cx - px)) / area;
+            let wb = ((cx - px) * (ay - py) - (cy - py) * (ax - px)) / area;
+            let wc = 1. - wa - wb;
+            if wa < 0. || wb < 0. || wc < 0. {
+                continue;
+            }
+            // 1 / depth is linear on the screen, depth itself isn't
+            let distance = 1. / (wa * az + wb * bz + wc * cz) / camera.focus_length;
+            hits[(y - top) * width + x].add(&p.tri, distance);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::{OutputMode, RayCaster};
+    use std::path::PathBuf;
+
+    #[test]
+    fn looks_like_the_ray_caster() {
+        let map = crate::loader::load(&PathBuf::from("level_pack_0/level_0.txt")).unwrap();
+        let camera = Camera {
+            pos: Vec3 {
+                x: map.start_pos.0,
+                y: map.start_pos.1,
+                z: map.start_pos.2,
+            },
+            ..Default::default()
+        };
+        for mode in [OutputMode::Color, OutputMode::HalfBlock] {
+            let screen = Screen::with_size(80, 30, mode, crate::color::Palette::TRUE_COLOR);
+            let cast = RayCaster.render(&screen, &camera, &map.bvh, &[], &map.light);
+            let raster = Rasterizer.render(&screen, &camera, &map.bvh, &[], &map.light);
+            assert_eq!(cast.len(), screen.rows());
+            assert!(cast.iter().flatten().any(|pixel| pixel.abs() > 1.));
+            // edges may land on a neighbouring pixel, everything else must match
+            let pixels = screen.w * screen.rows();
+            let same = (0..screen.rows())
+                .flat_map(|y| (0..screen.w).map(move |x| (x, y)))
+                .filter(|&(x, y)| (cast[y][x] - raster[y][x]).abs() < 1.)
+                .count();
+            assert!(same * 100 >= pixels * 97, "{same} of {pixels} pixels match");
+        }
+    }
+}
@@ -1,9 +1,15 @@
 use std::sync::{Arc, Mutex};
 
+use crate::bvh::{Bvh, RayHit};
+use crate::color::{Paint, Palette};
+use crate::light::Light;
+use crate::output::{self, out, outln};
+use crate::raster::Rasterizer;
 use crate::{camera::Camera, mat::*};
 use rayon::prelude::*;
 
-pub const RENDER_DIST: f64 = 30.;
+// how far something may be towards the sun and still cast a shadow
+const SHADOW_DIST: f64 = 100.;
 
 pub fn get_terminal_size() -> (usize, usize) {
     let (w, h) = crossterm::terminal::size().unwrap();
@@ -12,133 +18,376 @@ pub fn get_terminal_size() -> (usize, usize) {
     (w, h)
 }
 
+/// Turns the level and what moves in it into the colours `Screen::flush` prints
+pub trait Renderer {
+    fn render(
+        &self,
+        screen: &Screen,
+        camera: &Camera,
+        level: &Bvh,
+        dynamic: &[Tri],
+        light: &Light,
+    ) -> Vec<Vec<Vec3>>;
+}
+
+/// Casts a ray through every pixel (Möller–Trumbore against the BVH)
+pub struct RayCaster;
+
+impl Renderer for RayCaster {
+    fn render(
+        &self,
+        screen: &Screen,
+        camera: &Camera,
+        level: &Bvh,
+        dynamic: &[Tri],
+        light: &Light,
+    ) -> Vec<Vec<Vec3>> {
+        screen.render_bvh(camera, level, dynamic, light)
+    }
+}
+
+/// The renderers to pick from with `--renderer`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    RayCast,
+    Raster,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 2] = [Backend::RayCast, Backend::Raster];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::RayCast => "raycast",
+            Backend::Raster => "raster",
+        }
+    }
+
+    pub fn renderer(self) -> Box<dyn Renderer> {
+        match self {
+            Backend::RayCast => Box::new(RayCaster),
+            Backend::Raster => Box::new(Rasterizer),
+        }
+    }
+}
+
+/// Picks the renderer by name ("raycast" or "raster"), the ray caster without a name
+pub fn backend(kind: Option<&str>) -> Result<Backend, String> {
+    match kind {
+        None => Ok(Backend::RayCast),
+        Some(kind) => Backend::ALL
+            .into_iter()
+            .find(|backend| backend.name() == kind)
+            .ok_or(format!("unknown renderer '{kind}', use raycast or raster")),
+    }
+}
+
+/// How the colour buffer is turned into characters
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputMode {
+    // a coloured space per cell
+    Color,
+    // characters by brightness, for terminals without a background colour
+    Ascii,
+    // an upper half block per cell, coloured on top and bottom, twice the rows
+    HalfBlock,
+}
+
+impl OutputMode {
+    pub const ALL: [OutputMode; 3] = [OutputMode::Color, OutputMode::Ascii, OutputMode::HalfBlock];
+
+    /// As written in the settings file and shown on the settings screen
+    pub fn name(self) -> &'static str {
+        match self {
+            OutputMode::Color => "color",
+            OutputMode::Ascii => "ascii",
+            OutputMode::HalfBlock => "half-block",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<OutputMode> {
+        OutputMode::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    /// The mode `step` places further in `ALL`, wrapping around
+    pub fn cycle(self, step: isize) -> OutputMode {
+        let i = OutputMode::ALL
+            .iter()
+            .position(|mode| *mode == self)
+            .unwrap_or(0);
+        let len = OutputMode::ALL.len() as isize;
+        OutputMode::ALL[(i as isize + step).rem_euclid(len) as usize]
+    }
+
+    /// Rendered rows per terminal row
+    pub fn rows_per_cell(self) -> usize {
+        match self {
+            OutputMode::HalfBlock => 2,
+            _ => 1,
+        }
+    }
+}
+
+// a character of the terminal and its colours
+#[derive(Clone, Copy, PartialEq)]
+struct Cell {
+    c: char,
+    // unused for a space
+    fg: Option<Paint>,
+    bg: Paint,
+}
+
+// unchanged cells reprinted rather than moving the cursor past them, about as long as the move
+const MAX_SKIPPED_REPRINT: usize = 4;
+
+fn push_cell(out: &mut String, cell: &Cell, fg: &mut Option<Paint>, bg: &mut Option<Paint>) {
+    if *bg != Some(cell.bg) {
+        *out += &format!("\x1b[{}m", cell.bg.sgr(false));
+        *bg = Some(cell.bg);
+    }
+    if let Some(color) = cell.fg.filter(|color| *fg != Some(*color)) {
+        *out += &format!("\x1b[{}m", color.sgr(true));
+        *fg = Some(color);
+    }
+    out.push(cell.c);
+}
+
 #[derive(Clone)]
 pub struct Screen {
+    // size in terminal cells
     pub w: usize,
     pub h: usize,
+    pub mode: OutputMode,
+    pub palette: Palette,
+    // the last frame printed and its width, to only print what changed
+    last: Option<Vec<Cell>>,
+    last_width: usize,
 }
 
 impl Screen {
-    pub fn new() -> Self {
+    pub fn new(mode: OutputMode, palette: Palette) -> Self {
         let (w, h) = get_terminal_size();
         let mut stdout = std::io::stdout();
         crossterm::execute!(stdout, crossterm::cursor::Hide).unwrap();
         let h = h - 1;
 
         //clear screen
-        print!("\x1b[2J\x1b[H\r");
+        out!("\x1b[2J\x1b[H\r");
 
-        let buffer = vec![
-            vec![
-                Vec3 {
-                    x: 0.,
-                    y: 0.,
-                    z: 0.
-                };
-                w
-            ];
-            h
-        ];
-
-        Screen { w, h }
+        Screen::with_size(w, h, mode, palette)
     }
 
-    pub fn flush(&mut self, buffer: &[Vec<Vec3>], ascii: bool, extra: &str) {
-        // Create new string buffer
-        let mut pix_buffer = String::new();
-        let mut color = Vec3 {
-            x: 0.,
-            y: 0.,
-            z: 0.,
-        };
-        // Iterate through buffer
-        for y in 0..buffer.len() {
-            for x in 0..buffer[y].len() {
-                // Add ' ' (char if ascii is true) withrightcolor to string buffer
-                let mut c = ' ';
-                // set background
-                let mut command = "48";
-                let col = buffer[y][x];
-                if ascii {
-                    // set foreground
-                    command = "38";
-                    let chars = [
-                        ' ', '.', '-', ':', '_', '~', '/', 'c', 'r', 'x', '*', '%', '#', '8', '@',
-                    ];
-
-                    let s = col.x + col.y + col.z;
-                    let s = s * chars.len() as f64 / (255 * 3) as f64;
-                    let s = (s as usize).clamp(0, chars.len() - 1);
-                    c = chars[s];
-                }
-                if col != color {
-                    pix_buffer += &format!(
-                        "\x1b[{command};2;{};{};{}m{c}",
-                        col.x as u8, col.y as u8, col.z as u8
-                    );
-                    color = col;
-                } else {
-                    pix_buffer += " ";
-                }
-            }
-            pix_buffer += "\x1b[48;2;0;0;0m\r\n";
+    /// A screen that isn't the terminal, for rendering without showing it
+    pub const fn with_size(w: usize, h: usize, mode: OutputMode, palette: Palette) -> Self {
+        Screen {
+            w,
+            h,
+            mode,
+            palette,
+            last: None,
+            last_width: 0,
         }
-        pix_buffer += &format!("{:<1$}", extra, self.w);
+    }
+
+    /// The same view with `factor` times the cells each way, to render fewer pixels
+    pub fn scaled(&self, factor: f64) -> Screen {
+        let scale = |n: usize| ((n as f64 * factor).round() as usize).max(1);
+        Screen::with_size(scale(self.w), scale(self.h), self.mode, self.palette)
+    }
 
-        //Move cursor home and print string buffer
-        print!("\x1b[H{}", pix_buffer);
+    /// Rows of the colour buffer, more than `h` when a cell shows more than one
+    pub fn rows(&self) -> usize {
+        self.h * self.mode.rows_per_cell()
+    }
+
+    pub fn flush(&mut self, buffer: &[Vec<Vec3>], extra: &str) {
+        self.draw(buffer, extra);
 
         let (w, h) = get_terminal_size();
         if self.w != w || self.h != h - 1 {
-            print!("\x1b[2J\r");
+            out!("\x1b[2J\r");
             self.w = w;
             self.h = h - 1;
+            self.invalidate();
         }
     }
 
-    // Not used, left for benchmark against new version
-
-    pub fn render_pruned_mt(&self, camera: &Camera, mesh: &Mesh) -> Vec<Vec<Vec3>> {
-        let mut pruned_tris = Vec::with_capacity(mesh.tris().len());
-        let forward = Vec3 {
-            x: 0.,
-            y: 0.,
-            z: 1.,
-        };
-        let min_dim = self.w.min(self.h * 2) as f64 / 2.;
-        let pixel_coords = Vec3 {
-            x: -(self.w as f64 / 2.) / min_dim,
-            y: -(self.h as f64 / 2.) / min_dim,
-            z: camera.focus_length,
+    /// Writes the frame to the output without looking at the terminal size. A buffer of
+    /// another size, rendered at a lower resolution or before a resize, is stretched to fit.
+    pub fn draw(&mut self, buffer: &[Vec<Vec3>], extra: &str) {
+        let frame = if buffer.len() == self.rows() && buffer.iter().all(|row| row.len() == self.w) {
+            self.frame(buffer, extra)
+        } else {
+            self.frame(&stretch(buffer, self.w, self.rows()), extra)
         };
-        let angle = (forward.dot(pixel_coords) / pixel_coords.abs())
-            .acos()
-            .abs()
-            + 5.;
-        let forward = forward.rotate(camera.rotation);
-        for tri in mesh.tris() {
-            let mut valid = false;
-            for p in [tri.v0, tri.v1, tri.v2] {
-                // vector from pos to vertex
-                let v = p - camera.pos;
-                let v_abs = v.abs();
-                if v_abs < RENDER_DIST {
-                    valid = true;
-                    break;
+        out!("{}", frame);
+        output::flush();
+    }
+
+    // the escapes turning the last frame on the terminal into this one
+    fn frame(&mut self, buffer: &[Vec<Vec3>], extra: &str) -> String {
+        let cells = self.cells(buffer);
+        let width = buffer.first().map_or(0, Vec::len);
+        // a frame of another size has nothing to compare to
+        let last = self
+            .last
+            .take()
+            .filter(|last| last.len() == cells.len() && self.last_width == width);
+
+        // Create new string buffer, updates between the sync markers are shown at once
+        let mut pix_buffer = String::from("\x1b[?2026h");
+        // colours the terminal is set to, unknown at the start of the frame
+        let (mut fg, mut bg) = (None, None);
+        for (y, row) in cells.chunks(width.max(1)).enumerate() {
+            // where the cursor is on this row, after the last cell printed
+            let mut cursor = None;
+            for (x, cell) in row.iter().enumerate() {
+                let i = y * width + x;
+                if last.as_ref().is_some_and(|last| last[i] == *cell) {
+                    continue;
+                }
+                match cursor {
+                    Some(at) if at == x => (),
+                    // a few unchanged cells are shorter to print again than to jump over
+                    Some(at) if x - at <= MAX_SKIPPED_REPRINT => {
+                        for skipped in &row[at..x] {
+                            push_cell(&mut pix_buffer, skipped, &mut fg, &mut bg);
+                        }
+                    }
+                    _ => pix_buffer += &format!("\x1b[{};{}H", y + 1, x + 1),
+                }
+                push_cell(&mut pix_buffer, cell, &mut fg, &mut bg);
+                cursor = Some(x + 1);
+            }
+        }
+
+        // the info bar under the picture
+        let black = self.palette.paint(
+            Vec3 {
+                x: 0.,
+                y: 0.,
+                z: 0.,
+            },
+            0,
+            0,
+        );
+        pix_buffer += &format!(
+            "\x1b[{};1H\x1b[{}m{:<3$}",
+            self.h + 1,
+            black.sgr(false),
+            extra,
+            self.w
+        );
+        pix_buffer += "\x1b[?2026l";
+
+        self.last = Some(cells);
+        self.last_width = width;
+        pix_buffer
+    }
+
+    /// Forgets what is on the terminal, the next frame is printed whole.
+    /// Needed after anything else was drawn over the game.
+    pub fn invalidate(&mut self) {
+        self.last = None;
+    }
+
+    // what every terminal cell shows for the colour buffer
+    fn cells(&self, buffer: &[Vec<Vec3>]) -> Vec<Cell> {
+        let black = self.palette.paint(
+            Vec3 {
+                x: 0.,
+                y: 0.,
+                z: 0.,
+            },
+            0,
+            0,
+        );
+        let mut cells = vec![];
+        match self.mode {
+            OutputMode::Color => {
+                for (y, row) in buffer.iter().enumerate() {
+                    for (x, col) in row.iter().enumerate() {
+                        cells.push(Cell {
+                            c: ' ',
+                            fg: None,
+                            bg: self.palette.paint(*col, x, y),
+                        });
+                    }
                 }
-                if (v.dot(forward) / v_abs).acos() <= angle {
-                    valid = true;
-                    break;
+            }
+            OutputMode::Ascii => {
+                let chars = [
+                    ' ', '.', '-', ':', '_', '~', '/', 'c', 'r', 'x', '*', '%', '#', '8', '@',
+                ];
+                for (y, row) in buffer.iter().enumerate() {
+                    for (x, col) in row.iter().enumerate() {
+                        let s = col.x + col.y + col.z;
+                        let s = s * chars.len() as f64 / (255 * 3) as f64;
+                        let s = (s as usize).clamp(0, chars.len() - 1);
+                        cells.push(Cell {
+                            c: chars[s],
+                            fg: Some(self.palette.paint(*col, x, y)),
+                            bg: black,
+                        });
+                    }
                 }
             }
-            if valid {
-                pruned_tris.push(tri);
+            OutputMode::HalfBlock => {
+                // the upper pixel is the foreground of the block, the lower one the background
+                for (cell_y, rows) in buffer.chunks(2).enumerate() {
+                    for x in 0..rows[0].len() {
+                        let top = self.palette.paint(rows[0][x], x, cell_y * 2);
+                        let bottom = match rows.get(1) {
+                            Some(row) => self.palette.paint(row[x], x, cell_y * 2 + 1),
+                            None => top,
+                        };
+                        cells.push(if top == bottom {
+                            Cell {
+                                c: ' ',
+                                fg: None,
+                                bg: bottom,
+                            }
+                        } else {
+                            Cell {
+                                c: '\u{2580}',
+                                fg: Some(top),
+                                bg: bottom,
+                            }
+                        });
+                    }
+                }
             }
         }
-        self.render_mt(camera, &pruned_tris)
+        cells
     }
 
-    pub fn render_mt(&self, camera: &Camera, tris: &[Tri]) -> Vec<Vec<Vec3>> {
+    /// Casts the static level through its BVH, `dynamic` (enemies, the ghost) is tested tri by tri
+    pub fn render_bvh(
+        &self,
+        camera: &Camera,
+        bvh: &Bvh,
+        dynamic: &[Tri],
+        light: &Light,
+    ) -> Vec<Vec<Vec3>> {
+        self.render_rays(camera, bvh, light, |ray_o, ray_dir| {
+            // everything past the render distance is all fog anyway
+            let mut hit = RayHit::new(camera.max_ray_dist());
+            for tri in dynamic {
+                hit.test(tri, ray_o, ray_dir);
+            }
+            bvh.intersect(ray_o, ray_dir, &mut hit);
+            hit
+        })
+    }
+
+    // colours every pixel from what `cast` finds along its ray
+    fn render_rays(
+        &self,
+        camera: &Camera,
+        level: &Bvh,
+        light: &Light,
+        cast: impl Fn(Vec3, Vec3) -> RayHit + Sync,
+    ) -> Vec<Vec<Vec3>> {
         let mut buffer = vec![
             vec![
                 Vec3 {
@@ -148,53 +397,31 @@ impl Screen {
                 };
                 self.w
             ];
-            self.h
+            self.rows()
         ];
 
         buffer.par_iter_mut().enumerate().for_each(|(y, row)| {
             row.par_iter_mut().enumerate().for_each(|(x, pixel)| {
-                let mut min_dist = f64::MAX;
-                let mut color = Vec3 {
-                    x: 0.,
-                    y: 0.,
-                    z: 0.,
-                };
-                let min_dim = self.w.min(self.h * 2) as f64 / 2.;
-                let pixel_coords = Vec3 {
-                    x: (x as f64 - self.w as f64 / 2.) / min_dim,
-                    y: (y as f64 * 2. - self.h as f64 / 2.) / min_dim,
-                    z: camera.focus_length,
-                };
-                let pixel_coords = pixel_coords.rotate(camera.rotation);
-                let ray_dir = pixel_coords;
-                let ray_o = camera.pos;
-                let mut closet_idx = None;
-                tris.iter().enumerate().for_each(|(idx, tri)| {
-                    let (hit, distance) = tri.hit_mt(ray_o, ray_dir);
-                    if hit && distance < min_dist {
-                        min_dist = distance;
-
-                        closet_idx = Some(idx);
-                    }
-                });
-                if let Some(idx) = closet_idx {
-                    let tri = tris[idx];
-                    color = tri.color;
-                    let n = tri.normal();
-                    color = color
-                        * (n.dot(ray_dir * (-1.)) / (ray_dir.abs() * n.abs()))
-                            .abs()
-                            .clamp(0.5, 1.);
-                }
-                color = color * (1. - min_dist / RENDER_DIST);
-                *pixel = color;
+                let ray_dir = self.pixel_ray(camera, x, y);
+                *pixel = pixel_color(&cast(camera.pos, ray_dir), camera, ray_dir, light, level);
             });
         });
         buffer
     }
 
+    /// Direction of the ray through a pixel, its length is the distance unit of the renderers
+    pub fn pixel_ray(&self, camera: &Camera, x: usize, y: usize) -> Vec3 {
+        let min_dim = self.w.min(self.h * 2) as f64 / 2.;
+        let pixel_coords = Vec3 {
+            x: (x as f64 - self.w as f64 / 2.) / min_dim,
+            y: (y as f64 * 2. / self.mode.rows_per_cell() as f64 - self.h as f64 / 2.) / min_dim,
+            z: camera.focus_length,
+        };
+        pixel_coords.rotate(camera.rotation)
+    }
+
     fn print_info(&self, camera: &Camera, extra: &str) {
-        print!("\x1b[48;1;0m{:<1$}", extra, self.w);
+        out!("\x1b[48;1;0m{:<1$}", extra, self.w);
     }
 
     pub fn render_map(&self, map: &str, position: Vec3, grid_width: f64, grid_height: f64) {
@@ -237,13 +464,13 @@ impl Screen {
             }
         }
 
-        print!("\x1b[48;2;000;000;000m\x1b[\r");
+        out!("\x1b[48;2;000;000;000m\x1b[\r");
 
         let x_start = self.w / 2 - width / 2 - 2;
         let y_start = self.h / 2 - height / 2 - 3;
 
-        println!("\x1b[{};{}H*{:-^3$}*\r", y_start, x_start, "-", width + 4);
-        println!(
+        outln!("\x1b[{};{}H*{:-^3$}*\r", y_start, x_start, "-", width + 4);
+        outln!(
             "\x1b[{};{}H|{:^3$}|\r",
             y_start + 1,
             x_start,
@@ -253,28 +480,144 @@ impl Screen {
         let y_start = y_start + 2;
         for (i, row) in map.iter().enumerate() {
             //Move cursor
-            print!("\x1b[{};{}H", y_start + i, x_start);
+            out!("\x1b[{};{}H", y_start + i, x_start);
             let mut extra = 3;
 
             if i == pos_y {
                 extra += 36;
             }
             //println center aligned string
-            println!("| {:<1$}|\r", row, width + extra);
+            outln!("| {:<1$}|\r", row, width + extra);
         }
         let y_start = y_start + height;
 
-        println!(
+        outln!(
             "\x1b[{};{}H*{:-^3$}*\r",
             y_start + 1,
             x_start,
             "-",
             width + 4
         );
-        println!("\x1b[{};{}H|{:^3$}|\r", y_start, x_start, " ", width + 4);
+        outln!("\x1b[{};{}H|{:^3$}|\r", y_start, x_start, " ", width + 4);
     }
 }
 
+// nearest neighbour resize of the colour buffer
+fn stretch(buffer: &[Vec<Vec3>], w: usize, rows: usize) -> Vec<Vec<Vec3>> {
+    let src_rows = buffer.len();
+    let src_w = buffer.first().map_or(0, Vec::len);
+    if src_rows == 0 || src_w == 0 {
+        return vec![
+            vec![
+                Vec3 {
+                    x: 0.,
+                    y: 0.,
+                    z: 0.
+                };
+                w
+            ];
+            rows
+        ];
+    }
+    (0..rows)
+        .map(|y| {
+            let row = &buffer[y * src_rows / rows];
+            (0..w).map(|x| row[x * src_w / w]).collect()
+        })
+        .collect()
+}
+
+// smallest part of the resolution each way adaptive scaling goes down to
+const MIN_SCALE: f64 = 0.25;
+
+/// Renders fewer pixels while frames take longer than the target, and more again once
+/// there is time to spare
+pub struct ResolutionScale {
+    // seconds per frame
+    target: f64,
+    // smoothed, single slow frames shouldn't change the resolution
+    frame_time: f64,
+    factor: f64,
+}
+
+impl ResolutionScale {
+    pub fn new(target_fps: f64) -> Self {
+        ResolutionScale {
+            target: 1. / target_fps,
+            frame_time: 1. / target_fps,
+            factor: 1.,
+        }
+    }
+
+    /// Part of the resolution to render at each way, 1 is the whole terminal
+    pub fn factor(&self) -> f64 {
+        self.factor
+    }
+
+    /// Adjusts the resolution after a frame that took `dt` seconds
+    pub fn update(&mut self, dt: f64) {
+        self.frame_time = self.frame_time * 0.9 + dt * 0.1;
+        // far enough apart that it settles instead of going up and down every frame
+        if self.frame_time > self.target * 1.1 {
+            self.factor = (self.factor * 0.95).max(MIN_SCALE);
+        } else if self.frame_time < self.target * 0.8 {
+            self.factor = (self.factor * 1.02).min(1.);
+        }
+    }
+}
+
+/// Colour of a pixel from the closest tris along its ray from `ray_o`, into the fog with
+/// distance. Shadow rays go through `level`.
+pub fn pixel_color(
+    hit: &RayHit,
+    camera: &Camera,
+    ray_dir: Vec3,
+    light: &Light,
+    level: &Bvh,
+) -> Vec3 {
+    let max_dist = camera.max_ray_dist();
+    // moved towards the sun a little so the ray doesn't hit the tri it starts on
+    let shadowed = |dist: f64| {
+        move || {
+            level.occluded(
+                camera.pos + ray_dir * dist + light.sun * 0.001,
+                light.sun,
+                SHADOW_DIST,
+            )
+        }
+    };
+    // the texture gives the colour where the ray hits, fading out with distance where
+    // the pattern would only flicker between cells
+    let surface = |tri: Tri, dist: f64| {
+        let textured = tri.color_at(camera.pos + ray_dir * dist);
+        let detail = (1. - dist / max_dist).max(0.);
+        Tri {
+            color: tri.color + (textured - tri.color) * detail,
+            ..tri
+        }
+    };
+    // nothing hit is as far as it gets
+    let mut color = light.fog;
+    if let Some(tri) = hit.opaque {
+        let tri = surface(tri, hit.opaque_dist);
+        let shaded = light.shade(&tri, ray_dir, shadowed(hit.opaque_dist));
+        color = light.fog(shaded, hit.opaque_dist / max_dist);
+    }
+    // the closest translucent tri is blended over the opaque one if it is in front
+    if let Some(tri) = hit
+        .translucent
+        .filter(|_| hit.translucent_dist < hit.opaque_dist)
+    {
+        let tri = surface(tri, hit.translucent_dist);
+        let front = light.fog(
+            light.shade(&tri, ray_dir, shadowed(hit.translucent_dist)),
+            hit.translucent_dist / max_dist,
+        );
+        color = color * (1. - tri.alpha) + front * tri.alpha;
+    }
+    color
+}
+
 pub fn map_as_vec_of_floors(map: &str) -> Vec<Vec<&str>> {
     let spl = map.lines().collect::<Vec<&str>>();
     let mut res = vec![];
@@ -290,3 +633,73 @@ pub fn map_as_vec_of_floors(map: &str) -> Vec<Vec<&str>> {
     res.push(cur.clone());
     res
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prints_only_changed_cells() {
+        let mut screen = Screen::with_size(10, 3, OutputMode::Color, Palette::TRUE_COLOR);
+        let mut buffer = vec![
+            vec![
+                Vec3 {
+                    x: 0.,
+                    y: 0.,
+                    z: 0.
+                };
+                10
+            ];
+            3
+        ];
+        let first = screen.frame(&buffer, "");
+        assert!(first.starts_with("\x1b[?2026h") && first.ends_with("\x1b[?2026l"));
+        assert_eq!(first.matches(' ').count(), 30 + 10);
+
+        // only the info bar is printed again
+        let same = screen.frame(&buffer, "");
+        assert_eq!(same.matches('H').count(), 1);
+
+        buffer[1][3].x = 200.;
+        buffer[1][6].x = 200.;
+        let changed = screen.frame(&buffer, "");
+        // one jump to the first cell, the two between are printed over rather than skipped
+        assert!(changed.contains("\x1b[2;4H"));
+        assert_eq!(changed.matches('H').count(), 2);
+        assert_eq!(changed.matches(' ').count(), 4 + 10);
+
+        screen.invalidate();
+        assert_eq!(screen.frame(&buffer, "").matches(' ').count(), 30 + 10);
+    }
+
+    #[test]
+    fn stretches_smaller_buffers() {
+        let pixel = |x: f64| Vec3 { x, y: 0., z: 0. };
+        let small = vec![vec![pixel(1.), pixel(2.)], vec![pixel(3.), pixel(4.)]];
+        let big = stretch(&small, 4, 3);
+        let xs: Vec<Vec<f64>> = big
+            .iter()
+            .map(|row| row.iter().map(|p| p.x).collect())
+            .collect();
+        assert_eq!(xs, [[1., 1., 2., 2.], [1., 1., 2., 2.], [3., 3., 4., 4.]]);
+    }
+
+    #[test]
+    fn scale_follows_frame_time() {
+        let mut scale = ResolutionScale::new(50.);
+        for _ in 0..200 {
+            scale.update(0.05);
+        }
+        assert_eq!(scale.factor(), MIN_SCALE);
+        for _ in 0..30 {
+            scale.update(0.019);
+        }
+        // close to the target nothing changes
+        let settled = scale.factor();
+        assert_eq!(settled, MIN_SCALE);
+        for _ in 0..500 {
+            scale.update(0.005);
+        }
+        assert_eq!(scale.factor(), 1.);
+    }
+}
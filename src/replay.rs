@@ -0,0 +1,236 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::game::{TickInput, TICK_RATE};
+use crate::mat::Vec3;
+use crate::paths;
+
+// bump when the file layout changes
+// 1: [keys, rotation x, rotation y] per tick
+// 2: adds the camera position after the tick, for ghosts
+pub const REPLAY_VERSION: u64 = 2;
+
+/// Input and camera rotation of one simulation tick
+#[derive(Clone, Copy)]
+pub struct ReplayTick {
+    pub input: TickInput,
+    pub rotation: Vec3,
+    // missing in version 1 replays
+    pub pos: Option<Vec3>,
+}
+
+/// A recorded run, enough to play it back tick for tick through `Game::run`
+#[derive(Clone)]
+pub struct Replay {
+    pub level_id: String,
+    pub time: f64,
+    pub ticks: Vec<ReplayTick>,
+}
+
+impl Replay {
+    pub fn to_json(&self) -> Value {
+        let ticks: Vec<Value> = self
+            .ticks
+            .iter()
+            .map(|tick| {
+                let pos = tick.pos.unwrap_or(Vec3 {
+                    x: 0.,
+                    y: 0.,
+                    z: 0.,
+                });
+                json!([
+                    pack(tick.input),
+                    tick.rotation.x,
+                    tick.rotation.y,
+                    pos.x,
+                    pos.y,
+                    pos.z
+                ])
+            })
+            .collect();
+        json!({
+            "version": REPLAY_VERSION,
+            "level_id": self.level_id,
+            "time": self.time,
+            "ticks": ticks,
+        })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Replay, String> {
+        let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+        if !(1..=REPLAY_VERSION).contains(&version) {
+            return Err(format!("unsupported replay version {version}"));
+        }
+        let level_id = value
+            .get("level_id")
+            .and_then(Value::as_str)
+            .ok_or("replay has no level id")?
+            .to_string();
+        let time = value
+            .get("time")
+            .and_then(Value::as_f64)
+            .ok_or("replay has no time")?;
+        let mut ticks = vec![];
+        for tick in value
+            .get("ticks")
+            .and_then(Value::as_array)
+            .ok_or("replay has no ticks")?
+        {
+            let (Some(keys), Some(x), Some(y)) = (
+                tick.get(0).and_then(Value::as_u64),
+                tick.get(1).and_then(Value::as_f64),
+                tick.get(2).and_then(Value::as_f64),
+            ) else {
+                return Err("malformed replay tick".to_string());
+            };
+            let pos = match (
+                tick.get(3).and_then(Value::as_f64),
+                tick.get(4).and_then(Value::as_f64),
+                tick.get(5).and_then(Value::as_f64),
+            ) {
+                (Some(x), Some(y), Some(z)) if version >= 2 => Some(Vec3 { x, y, z }),
+                _ => None,
+            };
+            ticks.push(ReplayTick {
+                input: unpack(keys),
+                rotation: Vec3 { x, y, z: 0. },
+                pos,
+            });
+        }
+        Ok(Replay {
+            level_id,
+            time,
+            ticks,
+        })
+    }
+}
+
+// held keys as bit flags, in the order of the TickInput fields
+fn pack(input: TickInput) -> u64 {
+    [
+        input.forward,
+        input.back,
+        input.left,
+        input.right,
+        input.jump,
+        input.turn_left,
+        input.turn_right,
+        input.look_up,
+        input.look_down,
+    ]
+    .iter()
+    .enumerate()
+    .fold(0, |bits, (i, held)| bits | (*held as u64) << i)
+}
+
+fn unpack(bits: u64) -> TickInput {
+    let held = |i: u64| bits & (1 << i) != 0;
+    TickInput {
+        forward: held(0),
+        back: held(1),
+        left: held(2),
+        right: held(3),
+        jump: held(4),
+        turn_left: held(5),
+        turn_right: held(6),
+        look_up: held(7),
+        look_down: held(8),
+    }
+}
+
+/// Whether two finish times are the same number of ticks. Times add up a tick at a time,
+/// so runs of the same length can still differ in the last bits.
+pub fn same_time(a: f64, b: f64) -> bool {
+    (a * TICK_RATE).round() == (b * TICK_RATE).round()
+}
+
+pub fn load(path: &Path) -> Result<Replay, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let value: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    Replay::from_json(&value)
+}
+
+pub fn save(replay: &Replay, path: &Path) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, replay.to_json().to_string()).map_err(|e| e.to_string())
+}
+
+fn replay_dir() -> PathBuf {
+    paths::data_dir().join("replays")
+}
+
+pub fn last_path(level_id: &str) -> PathBuf {
+    replay_dir().join(format!("{level_id}.last.json"))
+}
+
+pub fn best_path(level_id: &str) -> PathBuf {
+    replay_dir().join(format!("{level_id}.best.json"))
+}
+
+/// Saves a finished run as the last run of its level, and as the best one if it beats it
+pub fn save_run(replay: &Replay) -> Result<(), String> {
+    save(replay, &last_path(&replay.level_id))?;
+    let best = load(&best_path(&replay.level_id));
+    if best.map_or(true, |best| replay.time < best.time) {
+        save(replay, &best_path(&replay.level_id))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::Camera;
+    use crate::game::tests::{level_0, level_0_run};
+    use crate::game::{run_headless, World};
+    use crate::input::ReplayInput;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn saved_runs_replay_to_the_same_time() {
+        let map = level_0();
+        let mut input = level_0_run();
+        // recorded like a played run, after every tick
+        let mut world = World::new(&map, Camera::default());
+        let mut ticks = vec![];
+        let time = loop {
+            let result = world.step(&mut input);
+            ticks.push(ReplayTick {
+                input: world.last_input,
+                rotation: world.camera.rotation,
+                pos: Some(world.camera.pos),
+            });
+            if let Some(result) = result {
+                break result.unwrap();
+            }
+        };
+        let replay = Replay {
+            level_id: "level_0".to_string(),
+            time,
+            ticks,
+        };
+
+        let path =
+            std::env::temp_dir().join(format!("termtrack-replay-{}.json", std::process::id()));
+        save(&replay, &path).unwrap();
+        let loaded = load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        // the camera is turned to the saved rotation every tick, it has to come back exactly
+        for (saved, loaded) in replay.ticks.iter().zip(&loaded.ticks) {
+            assert_eq!(saved.rotation.x.to_bits(), loaded.rotation.x.to_bits());
+            assert_eq!(
+                saved.pos.unwrap().z.to_bits(),
+                loaded.pos.unwrap().z.to_bits()
+            );
+        }
+
+        let mut playback = ReplayInput::new(&loaded, Arc::new(Mutex::new(false)));
+        let replayed = run_headless(&map, &mut playback, 60.).unwrap();
+        assert!(same_time(replayed, loaded.time));
+        assert!(!same_time(replayed + 1. / TICK_RATE, loaded.time));
+    }
+}
@@ -1,13 +1,21 @@
 use std::io::{stdin, Read};
 use std::sync::{Arc, Mutex};
-use std::{ffi::OsStr, fs, path::PathBuf, thread};
+use std::time::Duration;
+use std::{fs, path::PathBuf, thread};
 
-use device_query::{DeviceQuery, DeviceState, Keycode};
+use device_query::Keycode;
 use rodio::OutputStream;
 use rodio::OutputStreamHandle;
 use serde_json::{json, Value};
 
-use crate::{audio, mat, network, screens};
+use crate::keymap::{self, Action, Keymap};
+use crate::network::LeaderboardBackend;
+use crate::output::{out, outln};
+use crate::pack::Pack;
+use crate::profile::{self, Profile};
+use crate::progress::{LevelState, Progress};
+use crate::replay::{self, Replay};
+use crate::{audio, input, loader, screens};
 
 use crate::renderer;
 
@@ -84,23 +92,114 @@ $$$$$$$$/ $$$$$$$$/ $$$$$$$  |$$  \   /$$ |$$$$$$$$/ $$$$$$$  |/$$$$$$  |/$$$$$$
    $$ |   $$       |$$ |  $$ |$$ | $/  $$ |   $$ |   $$ |  $$ |$$ |  $$ |$$    $$/ $$ | $$  |
    $$/    $$$$$$$$/ $$/   $$/ $$/      $$/    $$/    $$/   $$/ $$/   $$/  $$$$$$/  $$/   $$/ "#; // min 130
 
-pub fn menu_print() {
+// instructions next to the level list, with the pack the levels are from
+fn menu_message(pack: &Pack) -> Vec<String> {
+    let mut message = vec![format!("Level pack: {}", pack.name)];
+    if let Some(author) = &pack.author {
+        message.push(format!("By {author}"));
+    }
+    let key = keymap::label;
+    message.extend([
+        " ".to_string(),
+        format!(
+            "Use |{}| and |{}| to navigate menu.",
+            key(Action::MenuUp),
+            key(Action::MenuDown)
+        ),
+        format!("Press |{}| to play.\n", key(Action::Confirm)),
+        " ".to_string(),
+        "How to win:".to_string(),
+        "Find the end.".to_string(),
+        " ".to_string(),
+        format!(
+            "Press |{}| to show leaderboard for chosen level",
+            key(Action::Leaderboard)
+        ),
+        format!(
+            "Press |{}| to watch your best run of chosen level",
+            key(Action::WatchReplay)
+        ),
+        format!(
+            "Press |{}| to turn campaign mode on or off",
+            key(Action::Campaign)
+        ),
+        format!("Press |{}| to change settings", key(Action::Settings)),
+    ]);
+    message
+}
+
+// the controls under the title, fitted to the width of the title
+fn controls_text(title: &str) -> Vec<String> {
+    let boxed = |actions: &[Action]| {
+        actions
+            .iter()
+            .map(|action| format!("|{}|", keymap::label(*action)))
+            .collect::<Vec<String>>()
+            .join(" ")
+    };
+    let plain = |actions: &[Action], sep: &str| {
+        actions
+            .iter()
+            .map(|action| keymap::label(*action))
+            .collect::<Vec<String>>()
+            .join(sep)
+    };
+    let moves = [
+        Action::MoveForward,
+        Action::MoveLeft,
+        Action::MoveBack,
+        Action::MoveRight,
+    ];
+    let turns = [
+        Action::TurnLeft,
+        Action::LookUp,
+        Action::LookDown,
+        Action::TurnRight,
+    ];
+    let jump = keymap::label(Action::Jump);
+    let map = keymap::label(Action::ToggleMap);
+    let exit = keymap::label(Action::Exit);
+    if title == TITLE_L || title == TITLE_M {
+        let movement = format!(
+            "{} - move   {} - rotate camera   | [{jump}] | - jump",
+            boxed(&moves),
+            boxed(&turns)
+        );
+        let other = format!("|{map}| - view map   |{exit}| - exit");
+        if title == TITLE_L {
+            vec![format!("{movement}   {other}")]
+        } else {
+            vec![movement, other]
+        }
+    } else {
+        let movement = format!(
+            "{} - move | {}  - rotate camera",
+            plain(&moves, ""),
+            plain(&turns, " ")
+        );
+        if title == TITLE_S {
+            vec![
+                format!("{movement} | [{jump}] - jump"),
+                format!("{map} - map | {exit} - exit"),
+            ]
+        } else {
+            vec![
+                movement,
+                format!("[{jump}] - jump | {map} - map | {exit} - exit"),
+            ]
+        }
+    }
+}
+
+pub fn menu_print(pack: &Pack) {
     let (screen_width, screen_height) = renderer::get_terminal_size();
     let screen_width = screen_width as u16;
     let screen_height = screen_height as u16;
-    let message = vec![
-        "Use |\u{1F845} | and |\u{1F847} | to navigate menu.",
-        "Press enter to play.\n",
-        " ",
-        "How to win:",
-        "Find the end.",
-        " ",
-        "Press |L| to show leaderboard for chosen level",
-    ];
+    let message = menu_message(pack);
     // print background image
-    print!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
+    out!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
     for _row in 0..=screen_height {
-        println!("{}\r", " ".repeat(screen_width as usize),)
+        outln!("{}\r", " ".repeat(screen_width as usize),)
     }
 
     let (title, gap) = match screen_width {
@@ -122,7 +221,7 @@ pub fn menu_print() {
     let x = screen_width / 2 - menu_width / 2;
     y += gap;
     for line in &lines {
-        println!(
+        outln!(
             "{esc}[{};{}H{}",
             y,
             screen_width / 2 - (line.len() as u16) / 2,
@@ -135,7 +234,7 @@ pub fn menu_print() {
     // print controls
 
     y += gap;
-    println!(
+    outln!(
         "{esc}[{};{}H{:-^3$}",
         y,
         screen_width / 2 - menu_width / 2,
@@ -145,73 +244,18 @@ pub fn menu_print() {
     );
     y += 1;
 
-    if title == TITLE_L {
-        println!(
-        "{esc}[{};{}H{:^3$}",
-        y,
-        screen_width / 2 - menu_width / 2,
-        "|W| |A| |S| |D| - move   |\u{1F844} | |\u{1F845} | |\u{1F847} | |\u{1F846} | - rotate camera   | [SPACEBAR] | - jump   |M| - view map   |E| - exit",
-        menu_width as usize,
-        esc = 27 as char
-    );
-    } else if title == TITLE_M {
-        println!(
-        "{esc}[{};{}H{:^3$}",
-        y,
-        screen_width / 2 - menu_width / 2,
-        "|W| |A| |S| |D| - move   |\u{1F844} | |\u{1F845} | |\u{1F847} | |\u{1F846} | - rotate camera   | [SPACEBAR] | - jump",
-        menu_width as usize,
-        esc = 27 as char
-    );
-        y += 1;
-        println!(
+    for line in controls_text(title) {
+        outln!(
             "{esc}[{};{}H{:^3$}",
             y,
             screen_width / 2 - menu_width / 2,
-            "|M| - view map   |E| - exit",
-            menu_width as usize,
-            esc = 27 as char
-        );
-    } else if title == TITLE_S {
-        println!(
-            "{esc}[{};{}H{:^3$}",
-            y,
-            screen_width / 2 - menu_width / 2,
-            "WASD - move | \u{1F844} \u{1F845} \u{1F847} \u{1F846}  - rotate camera | [SPACE] - jump",
-            menu_width as usize,
-            esc = 27 as char
-        );
-        y += 1;
-        println!(
-            "{esc}[{};{}H{:^3$}",
-            y,
-            screen_width / 2 - menu_width / 2,
-            "M - map | E - exit",
-            menu_width as usize,
-            esc = 27 as char
-        );
-    } else if title == TITLE_XS {
-        println!(
-            "{esc}[{};{}H{:^3$}",
-            y,
-            screen_width / 2 - menu_width / 2,
-            "WASD - move | \u{1F844} \u{1F845} \u{1F847} \u{1F846}  - rotate camera",
+            line,
             menu_width as usize,
             esc = 27 as char
         );
         y += 1;
-        println!(
-            "{esc}[{};{}H{:^3$}",
-            y,
-            screen_width / 2 - menu_width / 2,
-            "[SPACE] - jump | M - map | E - exit",
-            menu_width as usize,
-            esc = 27 as char
-        );
     }
-
-    y += 1;
-    println!(
+    outln!(
         "{esc}[{};{}H{:-^3$}",
         y,
         screen_width / 2 - menu_width / 2,
@@ -235,7 +279,7 @@ pub fn menu_print() {
     }
 
     for (n, line) in chopped_message.iter().enumerate() {
-        println!(
+        outln!(
             "{esc}[{};{}H{}",
             y + n as u16,
             screen_width / 2 + gap,
@@ -247,16 +291,56 @@ pub fn menu_print() {
     let box_width: u16 = menu_width / 2 - 2;
 }
 
+/// `text` cut to `width` characters, ending in "..." if it didn't fit
+fn shorten(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    text.chars()
+        .take(width.saturating_sub(3))
+        .collect::<String>()
+        + "..."
+}
+
+// a level in the menu list, with its best time or whether it's locked
+fn level_row(title: &str, state: LevelState, campaign: bool, width: usize) -> String {
+    let row = match state {
+        LevelState::Completed(time) => format!("{title}  {time:.2}s"),
+        LevelState::Locked if campaign => format!("{title} (locked)"),
+        _ => title.to_string(),
+    };
+    shorten(&row, width)
+}
+
+/// What the player picked in the menu, by index into the levels
+pub enum MenuChoice {
+    Play(usize),
+    Replay(usize, Replay),
+}
+
+/// What the menu keeps between visits
+#[derive(Default)]
+pub struct MenuState {
+    pub selected: usize,
+    // only unlocked levels can be played
+    pub campaign: bool,
+}
+
 pub fn menu(
-    levels: Vec<PathBuf>,
+    pack: &Pack,
+    state: &mut MenuState,
+    profile: &mut Profile,
     audio_handle: &OutputStreamHandle,
+    leader_board: &dyn LeaderboardBackend,
     focused: Arc<Mutex<bool>>,
-) -> usize {
-    let device_state = DeviceState::new();
-    let mut chosen_level = 0;
-    let level_names: Vec<&OsStr> = levels
+) -> MenuChoice {
+    let mut chosen_level = state.selected.min(pack.levels.len() - 1) as u16;
+    let progress = Progress::load(pack);
+    let levels: Vec<&PathBuf> = pack.levels.iter().map(|level| &level.path).collect();
+    let level_names: Vec<&str> = pack
+        .levels
         .iter()
-        .map(|path| path.file_stem().unwrap())
+        .map(|level| level.title.as_str())
         .collect();
     let (screen_width, screen_height) = renderer::get_terminal_size();
     let screen_width = screen_width as u16;
@@ -265,22 +349,14 @@ pub fn menu(
     if levels.len() < 7 {
         box_height = level_names.len() as u16;
     }
-    let message = vec![
-        "Use |\u{1F845} | and |\u{1F847} | to navigate menu.",
-        "Press enter to play.\n",
-        " ",
-        "How to win:",
-        "Find the end.",
-        " ",
-        "Press |L| to show leaderboard for chosen level",
-    ];
+    let message = menu_message(pack);
     let (_stream, audio_handle) = OutputStream::try_default().unwrap();
     audio::audio_loop(&audio_handle, "./sounds/menu.mp3");
 
     // print background image
-    print!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
+    out!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
     for _row in 0..=screen_height {
-        println!("{}\r", " ".repeat(screen_width as usize),)
+        outln!("{}\r", " ".repeat(screen_width as usize),)
     }
 
     // print title
@@ -303,7 +379,7 @@ pub fn menu(
     let x = screen_width / 2 - menu_width / 2;
     y += gap;
     for line in &lines {
-        println!(
+        outln!(
             "{esc}[{};{}H{}",
             y,
             screen_width / 2 - (line.len() as u16) / 2,
@@ -316,7 +392,7 @@ pub fn menu(
     // print controls
 
     y += gap;
-    println!(
+    outln!(
         "{esc}[{};{}H{:-^3$}",
         y,
         screen_width / 2 - menu_width / 2,
@@ -326,73 +402,18 @@ pub fn menu(
     );
     y += 1;
 
-    if title == TITLE_L {
-        println!(
-        "{esc}[{};{}H{:^3$}",
-        y,
-        screen_width / 2 - menu_width / 2,
-        "|W| |A| |S| |D| - move   |\u{1F844} | |\u{1F845} | |\u{1F847} | |\u{1F846} | - rotate camera   | [SPACEBAR] | - jump   |M| - view map   |E| - exit",
-        menu_width as usize,
-        esc = 27 as char
-    );
-    } else if title == TITLE_M {
-        println!(
-        "{esc}[{};{}H{:^3$}",
-        y,
-        screen_width / 2 - menu_width / 2,
-        "|W| |A| |S| |D| - move   |\u{1F844} | |\u{1F845} | |\u{1F847} | |\u{1F846} | - rotate camera   | [SPACEBAR] | - jump",
-        menu_width as usize,
-        esc = 27 as char
-    );
-        y += 1;
-        println!(
+    for line in controls_text(title) {
+        outln!(
             "{esc}[{};{}H{:^3$}",
             y,
             screen_width / 2 - menu_width / 2,
-            "|M| - view map   |E| - exit",
-            menu_width as usize,
-            esc = 27 as char
-        );
-    } else if title == TITLE_S {
-        println!(
-            "{esc}[{};{}H{:^3$}",
-            y,
-            screen_width / 2 - menu_width / 2,
-            "WASD - move | \u{1F844} \u{1F845} \u{1F847} \u{1F846}  - rotate camera | [SPACE] - jump",
-            menu_width as usize,
-            esc = 27 as char
-        );
-        y += 1;
-        println!(
-            "{esc}[{};{}H{:^3$}",
-            y,
-            screen_width / 2 - menu_width / 2,
-            "M - map | E - exit",
-            menu_width as usize,
-            esc = 27 as char
-        );
-    } else if title == TITLE_XS {
-        println!(
-            "{esc}[{};{}H{:^3$}",
-            y,
-            screen_width / 2 - menu_width / 2,
-            "WASD - move | \u{1F844} \u{1F845} \u{1F847} \u{1F846}  - rotate camera",
+            line,
             menu_width as usize,
             esc = 27 as char
         );
         y += 1;
-        println!(
-            "{esc}[{};{}H{:^3$}",
-            y,
-            screen_width / 2 - menu_width / 2,
-            "[SPACE] - jump | M - map | E - exit",
-            menu_width as usize,
-            esc = 27 as char
-        );
     }
-
-    y += 1;
-    println!(
+    outln!(
         "{esc}[{};{}H{:-^3$}",
         y,
         screen_width / 2 - menu_width / 2,
@@ -416,7 +437,7 @@ pub fn menu(
     }
 
     for (n, line) in chopped_message.iter().enumerate() {
-        println!(
+        outln!(
             "{esc}[{};{}H{}",
             y + n as u16,
             screen_width / 2 + gap,
@@ -430,11 +451,11 @@ pub fn menu(
     loop {
         // PRINT BOX
         // box upper line
-        println!(
+        outln!(
             "{esc}[{};{}H*{:-^3$}*",
             y,
             x,
-            "",
+            if state.campaign { " CAMPAIGN " } else { "" },
             (box_width - 2) as usize,
             esc = 27 as char
         );
@@ -454,14 +475,16 @@ pub fn menu(
 
         for i in lowest..highest {
             if i == chosen_level {
-                print!("{esc}[48;2;46;46;46m", esc = 27 as char);
+                out!("{esc}[48;2;46;46;46m", esc = 27 as char);
             }
 
-            let mut level_name = level_names[i as usize].to_str().unwrap().to_string();
-            if level_name.len() > box_width as usize - 2 {
-                level_name = level_name[0..(box_width as usize - 5)].to_string() + "...";
-            }
-            println!(
+            let level_name = level_row(
+                level_names[i as usize],
+                progress.state(pack, i as usize),
+                state.campaign,
+                box_width as usize - 2,
+            );
+            outln!(
                 "{esc}[{};{}H|{:^3$}|",
                 y + 1 + (i - lowest),
                 x,
@@ -469,11 +492,11 @@ pub fn menu(
                 (box_width - 2) as usize,
                 esc = 27 as char
             );
-            print!("{esc}[48;2;0;0;0m", esc = 27 as char);
+            out!("{esc}[48;2;0;0;0m", esc = 27 as char);
         }
 
         // box lower line
-        println!(
+        outln!(
             "{esc}[{};{}H*{:-^3$}*",
             y + box_height + 1,
             x,
@@ -482,14 +505,45 @@ pub fn menu(
             esc = 27 as char
         );
 
-        //chosen level id
-        let level_map = fs::read(&levels[chosen_level as usize]).unwrap();
+        // clear messages about the previously chosen level
+        for n in 0..4 {
+            outln!(
+                "{esc}[{};{}H{}",
+                y + box_height + 2 + n,
+                x,
+                " ".repeat(box_width as usize),
+                esc = 27 as char
+            );
+        }
 
-        let level_id = level_names[chosen_level as usize]
-            .to_str()
-            .unwrap()
-            .to_string()
-            + &crc32fast::hash(&level_map).to_string();
+        // what the pack says about the chosen level
+        let chosen = &pack.levels[chosen_level as usize];
+        let mut info = vec![];
+        if let Some(difficulty) = &chosen.difficulty {
+            info.push(format!("difficulty: {difficulty}"));
+        }
+        if let Some(par) = chosen.par {
+            info.push(format!("par: {par:.2}s"));
+        }
+        outln!(
+            "{esc}[{};{}H{:^3$}",
+            y + box_height + 2,
+            x,
+            info.join("   "),
+            box_width as usize,
+            esc = 27 as char
+        );
+
+        //chosen level id, from the file name so renaming a level in pack.json keeps its times
+        let level_map = fs::read_to_string(levels[chosen_level as usize]).unwrap_or_default();
+
+        let level_id = loader::level_id(
+            &levels[chosen_level as usize]
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy(),
+            &level_map,
+        );
 
         // println!(
         //     "{esc}[{};{}Hid: {}",
@@ -503,51 +557,117 @@ pub fn menu(
 
         //match input
         loop {
-            let keys = mat::get_keys_conditional(focused.lock().unwrap().clone());
+            let keys = input::menu_keys(focused.lock().unwrap().clone());
 
-            if keys.contains(&Keycode::Down) && chosen_level != level_names.len() as u16 - 1 {
+            if keymap::pressed(&keys, Action::MenuDown)
+                && chosen_level != level_names.len() as u16 - 1
+            {
                 chosen_level += 1;
                 audio::play_audio(&audio_handle, "./sounds/pop.mp3");
                 break;
             }
-            if keys.contains(&Keycode::Up) && chosen_level != 0 {
+            if keymap::pressed(&keys, Action::MenuUp) && chosen_level != 0 {
                 chosen_level = chosen_level.saturating_sub(1);
                 audio::play_audio(&audio_handle, "./sounds/pop.mp3");
                 break;
             }
-            if keys.contains(&Keycode::Enter) {
+            if keymap::pressed(&keys, Action::Confirm) {
                 // audio::play_audio(&audio_handle, "./sounds/enter.mp3");
                 // thread::sleep_ms(800);
 
-                return chosen_level as usize;
+                // show what's wrong with a broken level instead of starting it
+                if let Err(e) = loader::load(levels[chosen_level as usize]) {
+                    let error = e.to_string();
+                    let chars: Vec<char> = error.chars().collect();
+                    for (n, chunk) in chars.chunks(box_width as usize).enumerate() {
+                        outln!(
+                            "{esc}[{};{}H{:^3$}",
+                            y + box_height + 3 + n as u16,
+                            x,
+                            chunk.iter().collect::<String>(),
+                            box_width as usize,
+                            esc = 27 as char
+                        );
+                    }
+                    thread::sleep(Duration::from_millis(200));
+                    continue;
+                }
+                if state.campaign
+                    && progress.state(pack, chosen_level as usize) == LevelState::Locked
+                {
+                    outln!(
+                        "{esc}[{};{}H{:^3$}",
+                        y + box_height + 3,
+                        x,
+                        "Finish the previous level to unlock this one",
+                        box_width as usize,
+                        esc = 27 as char
+                    );
+                    thread::sleep(Duration::from_millis(200));
+                    continue;
+                }
+                state.selected = chosen_level as usize;
+                return MenuChoice::Play(chosen_level as usize);
             }
-            if keys.contains(&Keycode::L) {
+            if keymap::pressed(&keys, Action::WatchReplay) {
+                match replay::load(&replay::best_path(&level_id)) {
+                    Ok(best) => {
+                        state.selected = chosen_level as usize;
+                        return MenuChoice::Replay(chosen_level as usize, best);
+                    }
+                    Err(_) => {
+                        outln!(
+                            "{esc}[{};{}H{:^3$}",
+                            y + box_height + 3,
+                            x,
+                            "No finished run to watch yet",
+                            box_width as usize,
+                            esc = 27 as char
+                        );
+                        thread::sleep(Duration::from_millis(200));
+                        continue;
+                    }
+                }
+            }
+            if keymap::pressed(&keys, Action::Campaign) {
+                state.campaign = !state.campaign;
+                audio::play_audio(&audio_handle, "./sounds/pop.mp3");
+                break;
+            }
+            if keymap::pressed(&keys, Action::Settings) {
+                settings(profile, focused.clone());
+                menu_print(pack);
+                break;
+            }
+            if keymap::pressed(&keys, Action::Leaderboard) {
                 if !(leaderboard(
                     level_id,
-                    level_names[chosen_level as usize]
-                        .to_str()
-                        .unwrap()
-                        .to_string(),
+                    level_names[chosen_level as usize].to_string(),
+                    leader_board,
                     focused.clone(),
                 )) {
                     exit_app();
                 };
-                menu_print();
+                menu_print(pack);
                 break;
             }
-            if keys.contains(&Keycode::E) {
+            if keymap::pressed(&keys, Action::Exit) {
                 if screens::exit(focused.clone()) {
                     exit_app();
                 };
-                menu_print();
+                menu_print(pack);
                 break;
             }
         }
     }
 }
 
-pub fn leaderboard(level_id: String, level_name: String, focused: Arc<Mutex<bool>>) -> bool {
-    let device_state = DeviceState::new();
+pub fn leaderboard(
+    level_id: String,
+    level_name: String,
+    backend: &dyn LeaderboardBackend,
+    focused: Arc<Mutex<bool>>,
+) -> bool {
     let (screen_width, screen_height) = renderer::get_terminal_size();
     let screen_width = screen_width as u16;
     let screen_height = screen_height as u16;
@@ -557,28 +677,19 @@ pub fn leaderboard(level_id: String, level_name: String, focused: Arc<Mutex<bool
     let margin = 3;
     let mut scroll: usize = 0;
 
-    let mut leader_board = network::get_leader_board(&level_id);
-    let leader_vec = leader_board.as_array_mut().expect("leaderboard error");
+    let leader_vec = backend.results(&level_id);
     let take = leader_vec.len().min(screen_height as usize - 10);
 
-    leader_vec.sort_by_key(|val| {
-        (val.get("time")
-            .expect("leaderboard format wrong")
-            .as_f64()
-            .expect("leaderboard format wrong")
-            * 1000.) as usize
-    });
-
     // print background image
-    print!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
+    out!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
     for _row in 0..=screen_height {
-        println!("{}\r", " ".repeat(screen_width as usize),)
+        outln!("{}\r", " ".repeat(screen_width as usize),)
     }
 
     let level_name = level_name.to_uppercase();
 
     y += 3;
-    println!(
+    outln!(
         "{esc}[{};{}H{:^3$}",
         y,
         x,
@@ -587,7 +698,7 @@ pub fn leaderboard(level_id: String, level_name: String, focused: Arc<Mutex<bool
         esc = 27 as char
     );
     y += 1;
-    println!(
+    outln!(
         "{esc}[{};{}H{:-^3$}",
         y,
         x,
@@ -600,19 +711,15 @@ pub fn leaderboard(level_id: String, level_name: String, focused: Arc<Mutex<bool
     loop {
         // print leaderboard
 
-        for (i, result) in leader_vec.iter().skip(scroll).take(take).enumerate() {
-            let mut name = result.get("name").unwrap().as_str().unwrap().to_string();
-            let time = result.get("time").unwrap().as_f64().unwrap();
+        for (i, (name, time)) in leader_vec.iter().skip(scroll).take(take).enumerate() {
             let max_width = box_width
                 - 2 * margin
                 - format!("{:.2}", time).len() as u16
                 - 7
                 - i.to_string().len() as u16;
-            if name.len() > max_width as usize {
-                name = name[0..(max_width as usize - 3)].to_string() + "...";
-            }
+            let name = shorten(name, max_width as usize);
 
-            println!(
+            outln!(
                 "{esc}[{};{}H{:<3$}",
                 y + i as u16,
                 x + margin,
@@ -624,7 +731,7 @@ pub fn leaderboard(level_id: String, level_name: String, focused: Arc<Mutex<bool
 
         // print lines
 
-        println!(
+        outln!(
             "{esc}[{};{}H{:-^3$}",
             y + take as u16,
             x,
@@ -632,14 +739,14 @@ pub fn leaderboard(level_id: String, level_name: String, focused: Arc<Mutex<bool
             (box_width) as usize,
             esc = 27 as char
         );
-        println!(
+        outln!(
             "{esc}[{};{}H{}",
             y + take as u16 + 1,
             x,
             "Use |\u{1F845} | and |\u{1F847} | to scroll",
             esc = 27 as char
         );
-        println!(
+        outln!(
             "{esc}[{};{}H{}",
             y + take as u16 + 2,
             x,
@@ -651,20 +758,20 @@ pub fn leaderboard(level_id: String, level_name: String, focused: Arc<Mutex<bool
 
         //match input
         loop {
-            let keys = mat::get_keys_conditional(focused.lock().unwrap().clone());
+            let keys = input::menu_keys(focused.lock().unwrap().clone());
 
-            if keys.contains(&Keycode::Up) {
+            if keymap::pressed(&keys, Action::MenuUp) {
                 scroll = scroll.saturating_sub(1);
                 break;
             }
-            if keys.contains(&Keycode::Down) {
+            if keymap::pressed(&keys, Action::MenuDown) {
                 if scroll < leader_vec.len() - take {
                     scroll += 1;
                 }
 
                 break;
             }
-            if keys.contains(&Keycode::E) {
+            if keymap::pressed(&keys, Action::Exit) {
                 return true;
             }
         }
@@ -672,7 +779,6 @@ pub fn leaderboard(level_id: String, level_name: String, focused: Arc<Mutex<bool
 }
 
 pub fn game_over(arg: &str, focused: Arc<Mutex<bool>>) -> bool {
-    let device_state = DeviceState::new();
     let (screen_width, screen_height) = renderer::get_terminal_size();
     let screen_width = screen_width as u16;
     let screen_height = screen_height as u16;
@@ -687,14 +793,14 @@ pub fn game_over(arg: &str, focused: Arc<Mutex<bool>>) -> bool {
 
     loop {
         // print background image
-        print!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
+        out!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
         for _row in 0..=screen_height {
-            println!("{}\r", " ".repeat(screen_width as usize),)
+            outln!("{}\r", " ".repeat(screen_width as usize),)
         }
 
         // print menu
-        print!("{esc}[48;2;0;0;0m", esc = 27 as char);
-        println!(
+        out!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        outln!(
             "{esc}[{};{}H*{:-^3$}*",
             start_y,
             start_x,
@@ -702,7 +808,7 @@ pub fn game_over(arg: &str, focused: Arc<Mutex<bool>>) -> bool {
             (box_width - 2) as usize,
             esc = 27 as char
         );
-        println!(
+        outln!(
             "{esc}[{};{}H|{: ^3$}|",
             start_y + 1,
             start_x,
@@ -710,7 +816,7 @@ pub fn game_over(arg: &str, focused: Arc<Mutex<bool>>) -> bool {
             (box_width - 2) as usize,
             esc = 27 as char
         );
-        println!(
+        outln!(
             "{esc}[{};{}H|{: ^3$}|",
             start_y + 2,
             start_x,
@@ -719,9 +825,9 @@ pub fn game_over(arg: &str, focused: Arc<Mutex<bool>>) -> bool {
             esc = 27 as char
         );
         if try_again {
-            print!("{esc}[48;2;46;46;46m", esc = 27 as char);
+            out!("{esc}[48;2;46;46;46m", esc = 27 as char);
         }
-        println!(
+        outln!(
             "{esc}[{};{}H|{: ^3$}|",
             start_y + 3,
             start_x,
@@ -729,11 +835,11 @@ pub fn game_over(arg: &str, focused: Arc<Mutex<bool>>) -> bool {
             (box_width - 2) as usize,
             esc = 27 as char
         );
-        print!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        out!("{esc}[48;2;0;0;0m", esc = 27 as char);
         if !try_again {
-            print!("{esc}[48;2;46;46;46m", esc = 27 as char);
+            out!("{esc}[48;2;46;46;46m", esc = 27 as char);
         }
-        println!(
+        outln!(
             "{esc}[{};{}H|{: ^3$}|",
             start_y + 4,
             start_x,
@@ -742,8 +848,8 @@ pub fn game_over(arg: &str, focused: Arc<Mutex<bool>>) -> bool {
             esc = 27 as char
         );
 
-        print!("{esc}[48;2;0;0;0m", esc = 27 as char);
-        println!(
+        out!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        outln!(
             "{esc}[{};{}H*{:-^3$}*",
             start_y + 5,
             start_x,
@@ -756,22 +862,22 @@ pub fn game_over(arg: &str, focused: Arc<Mutex<bool>>) -> bool {
 
         //match input
         loop {
-            let keys = mat::get_keys_conditional(focused.lock().unwrap().clone());
+            let keys = input::menu_keys(focused.lock().unwrap().clone());
 
-            if keys.contains(&Keycode::Down) {
+            if keymap::pressed(&keys, Action::MenuDown) {
                 try_again = !try_again;
                 audio::play_audio(&audio_handle, "./sounds/pop.mp3");
                 break;
             }
-            if keys.contains(&Keycode::Up) {
+            if keymap::pressed(&keys, Action::MenuUp) {
                 try_again = !try_again;
                 audio::play_audio(&audio_handle, "./sounds/pop.mp3");
                 break;
             }
-            if keys.contains(&Keycode::Enter) {
+            if keymap::pressed(&keys, Action::Confirm) {
                 return try_again;
             }
-            if keys.contains(&Keycode::E) {
+            if keymap::pressed(&keys, Action::Exit) {
                 if exit(focused.clone()) {
                     exit_app();
                 }
@@ -781,9 +887,17 @@ pub fn game_over(arg: &str, focused: Arc<Mutex<bool>>) -> bool {
     }
 }
 
-pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<bool>>) -> u8 {
+pub fn finish(
+    time: f64,
+    // personal best before this run
+    best: Option<f64>,
+    name: &mut String,
+    level_name: &str,
+    level_map: &str,
+    backend: &dyn LeaderboardBackend,
+    focused: Arc<Mutex<bool>>,
+) -> u8 {
     // get device state for input
-    let device_state = DeviceState::new();
 
     // get terminal size
     let (screen_width, screen_height) = renderer::get_terminal_size();
@@ -794,20 +908,10 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
     let (_stream, audio_handle) = OutputStream::try_default().unwrap();
 
     //name + crc32(map) == id
-    let id = level_name.to_string() + &crc32fast::hash(level_map.as_bytes()).to_string();
+    let id = loader::level_id(level_name, level_map);
 
     // get the leaderboard
-    let mut leader_board = network::get_leader_board(&id);
-    let leader_vec = leader_board.as_array_mut().expect("leaderboard error");
-    leader_vec.sort_by_key(|val| {
-        (val.get("time")
-            .expect("leaderboard format wrong")
-            .as_f64()
-            .expect("leaderboard format wrong")
-            * 1000.) as usize
-    });
-    let mut name = String::new();
-
+    let leader_vec = backend.results(&id);
     let take = leader_vec.len().min(5);
 
     // set the size of the textbox
@@ -821,14 +925,14 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
 
     loop {
         // print background image
-        print!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
+        out!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
         for _row in 0..=screen_height {
-            println!("{}\r", " ".repeat(screen_width as usize),)
+            outln!("{}\r", " ".repeat(screen_width as usize),)
         }
 
         // print menu
-        print!("{esc}[48;2;0;0;0m", esc = 27 as char);
-        println!(
+        out!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        outln!(
             "{esc}[{};{}H*{:-^3$}*",
             start_y,
             start_x,
@@ -836,7 +940,7 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
             (box_width - 2) as usize,
             esc = 27 as char
         );
-        println!(
+        outln!(
             "{esc}[{};{}H|{:^3$}|",
             start_y + 1,
             start_x,
@@ -844,15 +948,19 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
             (box_width - 2) as usize,
             esc = 27 as char
         );
-        println!(
+        outln!(
             "{esc}[{};{}H|{:^3$}|",
             start_y + 2,
             start_x,
-            format!("Time: {:.2}s", time),
+            match best {
+                Some(best) if best <= time => format!("Time: {:.2}s (best {:.2}s)", time, best),
+                Some(_) => format!("Time: {:.2}s - new best!", time),
+                None => format!("Time: {:.2}s", time),
+            },
             (box_width - 2) as usize,
             esc = 27 as char
         );
-        println!(
+        outln!(
             "{esc}[{};{}H|{:^3$}|",
             start_y + 3,
             start_x,
@@ -861,18 +969,18 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
             esc = 27 as char
         );
         if chosen == 0 {
-            print!("{esc}[48;2;46;46;46m", esc = 27 as char);
+            out!("{esc}[48;2;46;46;46m", esc = 27 as char);
         }
-        println!(
+        outln!(
             "{esc}[{};{}H|{:^3$}|",
             start_y + 4,
             start_x,
-            &name,
+            &*name,
             (box_width - 2) as usize,
             esc = 27 as char
         );
-        print!("{esc}[48;2;0;0;0m", esc = 27 as char);
-        println!(
+        out!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        outln!(
             "{esc}[{};{}H|{:^3$}|",
             start_y + 5,
             start_x,
@@ -880,15 +988,11 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
             (box_width - 2) as usize,
             esc = 27 as char
         );
-        for (i, result) in leader_vec.iter().take(take).enumerate() {
-            let mut name = result.get("name").unwrap().as_str().unwrap().to_string();
-            let time = result.get("time").unwrap().as_f64().unwrap();
+        for (i, (name, time)) in leader_vec.iter().take(take).enumerate() {
             let max_width = box_width - 10 - format!("{:.2}", time).len() as u16 - 1;
-            if name.len() > max_width as usize {
-                name = name[0..(max_width as usize - 3)].to_string() + "...";
-            }
+            let name = shorten(name, max_width as usize);
 
-            println!(
+            outln!(
                 "{esc}[{};{}H| {:<3$} |",
                 start_y + 6 + i as u16,
                 start_x,
@@ -898,7 +1002,7 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
             );
         }
 
-        println!(
+        outln!(
             "{esc}[{};{}H|{:^3$}|",
             start_y + 6 + take as u16,
             start_x,
@@ -907,7 +1011,7 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
             esc = 27 as char
         );
 
-        println!(
+        outln!(
             "{esc}[{};{}H|{: ^3$}|",
             start_y + box_height - 2,
             start_x,
@@ -916,9 +1020,9 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
             esc = 27 as char
         );
         if chosen == 1 {
-            print!("{esc}[48;2;46;46;46m", esc = 27 as char);
+            out!("{esc}[48;2;46;46;46m", esc = 27 as char);
         }
-        println!(
+        outln!(
             "{esc}[{};{}H|{: ^3$}|",
             start_y + box_height - 1,
             start_x,
@@ -926,11 +1030,11 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
             (box_width - 2) as usize,
             esc = 27 as char
         );
-        print!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        out!("{esc}[48;2;0;0;0m", esc = 27 as char);
         if chosen == 2 {
-            print!("{esc}[48;2;46;46;46m", esc = 27 as char);
+            out!("{esc}[48;2;46;46;46m", esc = 27 as char);
         }
-        println!(
+        outln!(
             "{esc}[{};{}H|{: ^3$}|",
             start_y + box_height,
             start_x,
@@ -939,8 +1043,8 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
             esc = 27 as char
         );
 
-        print!("{esc}[48;2;0;0;0m", esc = 27 as char);
-        println!(
+        out!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        outln!(
             "{esc}[{};{}H*{:-^3$}*",
             start_y + box_height + 1,
             start_x,
@@ -960,14 +1064,14 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
 
         //match input
         'input_loop: loop {
-            let keys = mat::get_keys_conditional(focused.lock().unwrap().clone());
+            let keys = input::menu_keys(focused.lock().unwrap().clone());
 
             for key in KEYS_KEYCODE {
                 if keys.contains(&key.0) && chosen == 0 {
                     if keys.contains(&Keycode::LShift) {
-                        name += key.2;
+                        *name += key.2;
                     } else {
-                        name += key.1;
+                        *name += key.1;
                     }
                     break 'input_loop;
                 }
@@ -978,28 +1082,29 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
                 break;
             }
 
-            if keys.contains(&Keycode::Down) && chosen != 2 {
+            if keymap::pressed(&keys, Action::MenuDown) && chosen != 2 {
                 chosen += 1;
                 audio::play_audio(&audio_handle, "./sounds/pop.mp3");
                 break;
             }
-            if keys.contains(&Keycode::Up) && chosen != 0 {
+            if keymap::pressed(&keys, Action::MenuUp) && chosen != 0 {
                 chosen -= 1;
                 audio::play_audio(&audio_handle, "./sounds/pop.mp3");
                 break;
             }
-            if keys.contains(&Keycode::Enter) && chosen != 0 {
+            if keymap::pressed(&keys, Action::Confirm) && chosen != 0 {
                 if !name.is_empty() {
-                    network::log_result(&id, &name, time);
+                    // a failed upload shouldn't stop the player from continuing
+                    let _ = backend.log_result(&id, name, time);
                 }
                 return chosen;
             }
-            if keys.contains(&Keycode::Enter) && chosen == 0 {
+            if keymap::pressed(&keys, Action::Confirm) && chosen == 0 {
                 chosen += 1;
                 audio::play_audio(&audio_handle, "./sounds/pop.mp3");
                 break;
             }
-            if keys.contains(&Keycode::E) && chosen != 0 {
+            if keymap::pressed(&keys, Action::Exit) && chosen != 0 {
                 if exit(focused.clone()) {
                     exit_app();
                 }
@@ -1010,7 +1115,6 @@ pub fn finish(time: f64, level_name: &str, level_map: &str, focused: Arc<Mutex<b
 }
 
 pub fn exit(focused: Arc<Mutex<bool>>) -> bool {
-    let device_state = DeviceState::new();
     let (screen_width, screen_height) = renderer::get_terminal_size();
     let screen_width = screen_width as u16;
     let screen_height = screen_height as u16;
@@ -1026,14 +1130,14 @@ pub fn exit(focused: Arc<Mutex<bool>>) -> bool {
 
     loop {
         // print background image
-        print!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
+        out!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
         for _row in 0..=screen_height {
-            println!("{}\r", " ".repeat(screen_width as usize),)
+            outln!("{}\r", " ".repeat(screen_width as usize),)
         }
 
         // print menu
-        print!("{esc}[48;2;0;0;0m", esc = 27 as char);
-        println!(
+        out!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        outln!(
             "{esc}[{};{}H*{:-^3$}*",
             start_y,
             start_x,
@@ -1041,7 +1145,7 @@ pub fn exit(focused: Arc<Mutex<bool>>) -> bool {
             (box_width - 2) as usize,
             esc = 27 as char
         );
-        println!(
+        outln!(
             "{esc}[{};{}H|{: ^3$}|",
             start_y + 1,
             start_x,
@@ -1049,7 +1153,7 @@ pub fn exit(focused: Arc<Mutex<bool>>) -> bool {
             (box_width - 2) as usize,
             esc = 27 as char
         );
-        println!(
+        outln!(
             "{esc}[{};{}H|{: ^3$}|",
             start_y + 2,
             start_x,
@@ -1058,9 +1162,9 @@ pub fn exit(focused: Arc<Mutex<bool>>) -> bool {
             esc = 27 as char
         );
         if exit {
-            print!("{esc}[48;2;46;46;46m", esc = 27 as char);
+            out!("{esc}[48;2;46;46;46m", esc = 27 as char);
         }
-        println!(
+        outln!(
             "{esc}[{};{}H|{: ^3$}|",
             start_y + 3,
             start_x,
@@ -1068,11 +1172,11 @@ pub fn exit(focused: Arc<Mutex<bool>>) -> bool {
             (box_width - 2) as usize,
             esc = 27 as char
         );
-        print!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        out!("{esc}[48;2;0;0;0m", esc = 27 as char);
         if !exit {
-            print!("{esc}[48;2;46;46;46m", esc = 27 as char);
+            out!("{esc}[48;2;46;46;46m", esc = 27 as char);
         }
-        println!(
+        outln!(
             "{esc}[{};{}H|{: ^3$}|",
             start_y + 4,
             start_x,
@@ -1081,8 +1185,8 @@ pub fn exit(focused: Arc<Mutex<bool>>) -> bool {
             esc = 27 as char
         );
 
-        print!("{esc}[48;2;0;0;0m", esc = 27 as char);
-        println!(
+        out!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        outln!(
             "{esc}[{};{}H*{:-^3$}*",
             start_y + 5,
             start_x,
@@ -1095,32 +1199,361 @@ pub fn exit(focused: Arc<Mutex<bool>>) -> bool {
 
         //match input
         loop {
-            let keys = mat::get_keys_conditional(focused.lock().unwrap().clone());
+            let keys = input::menu_keys(focused.lock().unwrap().clone());
 
-            if keys.contains(&Keycode::Down) || keys.contains(&Keycode::Up) {
+            if keymap::pressed(&keys, Action::MenuDown) || keymap::pressed(&keys, Action::MenuUp) {
                 exit = !exit;
                 audio::play_audio(&audio_handle, "./sounds/pop.mp3");
                 break;
             }
 
-            if keys.contains(&Keycode::Enter) {
+            if keymap::pressed(&keys, Action::Confirm) {
                 return exit;
             }
         }
     }
 }
 
+/// Edits the name and settings of the profile, saved when leaving the screen
+pub fn settings(profile: &mut Profile, focused: Arc<Mutex<bool>>) {
+    let (screen_width, screen_height) = renderer::get_terminal_size();
+    let screen_width = screen_width as u16;
+    let screen_height = screen_height as u16;
+
+    let box_width: u16 = 40;
+    let box_height = 12;
+
+    let (_stream, audio_handle) = OutputStream::try_default().unwrap();
+
+    let start_x = screen_width / 2 - box_width / 2;
+    let start_y = screen_height / 2 - box_height / 2;
+    // name, volume, fov, view distance, fog, render mode, shadows, target fps, controls, back
+    let mut chosen = 0;
+
+    loop {
+        // print background image
+        out!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
+        for _row in 0..=screen_height {
+            outln!("{}\r", " ".repeat(screen_width as usize),)
+        }
+
+        // print menu
+        out!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        outln!(
+            "{esc}[{};{}H*{:-^3$}*",
+            start_y,
+            start_x,
+            " SETTINGS ",
+            (box_width - 2) as usize,
+            esc = 27 as char
+        );
+        outln!(
+            "{esc}[{};{}H|{: ^3$}|",
+            start_y + 1,
+            start_x,
+            format!(
+                "|{}| |{}| to change",
+                keymap::label(Action::MenuLeft),
+                keymap::label(Action::MenuRight)
+            ),
+            (box_width - 2) as usize,
+            esc = 27 as char
+        );
+        let settings = &profile.settings;
+        let rows = [
+            format!("Name: {}", profile.name),
+            format!("Volume: {:.0}%", settings.volume * 100.),
+            format!("Field of view: {:.0}\u{b0}", settings.fov),
+            format!("View distance: {:.0}", settings.view_dist),
+            format!("Fog starts at: {:.0}%", settings.fog_start * 100.),
+            format!("Render mode: {}", settings.output.name()),
+            format!("Shadows: {}", if settings.shadows { "on" } else { "off" }),
+            if settings.target_fps > 0. {
+                format!("Lower resolution below {:.0} fps", settings.target_fps)
+            } else {
+                "Lower resolution: off".to_string()
+            },
+            "Controls".to_string(),
+            "Back".to_string(),
+        ];
+        for (i, row) in rows.iter().enumerate() {
+            if i == chosen {
+                out!("{esc}[48;2;46;46;46m", esc = 27 as char);
+            }
+            outln!(
+                "{esc}[{};{}H|{: ^3$}|",
+                start_y + 2 + i as u16,
+                start_x,
+                row,
+                (box_width - 2) as usize,
+                esc = 27 as char
+            );
+            out!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        }
+        outln!(
+            "{esc}[{};{}H*{:-^3$}*",
+            start_y + box_height,
+            start_x,
+            "",
+            (box_width - 2) as usize,
+            esc = 27 as char
+        );
+
+        thread::sleep(Duration::from_millis(200));
+
+        //match input
+        'input_loop: loop {
+            let keys = input::menu_keys(*focused.lock().unwrap());
+
+            if chosen == 0 {
+                for key in KEYS_KEYCODE {
+                    if keys.contains(&key.0) {
+                        if keys.contains(&Keycode::LShift) {
+                            profile.name += key.2;
+                        } else {
+                            profile.name += key.1;
+                        }
+                        break 'input_loop;
+                    }
+                }
+                if keys.contains(&Keycode::Backspace) {
+                    profile.name.pop();
+                    break;
+                }
+            }
+
+            if keymap::pressed(&keys, Action::MenuDown) && chosen != rows.len() - 1 {
+                chosen += 1;
+                audio::play_audio(&audio_handle, "./sounds/pop.mp3");
+                break;
+            }
+            if keymap::pressed(&keys, Action::MenuUp) && chosen != 0 {
+                chosen -= 1;
+                audio::play_audio(&audio_handle, "./sounds/pop.mp3");
+                break;
+            }
+            let step = match (
+                keymap::pressed(&keys, Action::MenuLeft),
+                keymap::pressed(&keys, Action::MenuRight),
+            ) {
+                (true, false) => -1.,
+                (false, true) => 1.,
+                _ => 0.,
+            };
+            if step != 0. && (1..=7).contains(&chosen) {
+                let settings = &mut profile.settings;
+                match chosen {
+                    1 => {
+                        settings.volume = (settings.volume + step * 0.1).clamp(0., 1.);
+                        audio::set_volume(settings.volume);
+                    }
+                    2 => {
+                        settings.fov = (settings.fov.round() + step * 5.)
+                            .clamp(profile::MIN_FOV, profile::MAX_FOV)
+                    }
+                    3 => {
+                        settings.view_dist = (settings.view_dist.round() + step * 5.)
+                            .clamp(profile::MIN_VIEW_DIST, profile::MAX_VIEW_DIST)
+                    }
+                    4 => {
+                        settings.fog_start = ((settings.fog_start * 10.).round() + step)
+                            .clamp(0., profile::MAX_FOG_START * 10.)
+                            / 10.
+                    }
+                    5 => settings.output = settings.output.cycle(step as isize),
+                    6 => settings.shadows = !settings.shadows,
+                    _ => {
+                        settings.target_fps = (settings.target_fps.round() + step * 5.)
+                            .clamp(0., profile::MAX_TARGET_FPS)
+                    }
+                }
+                audio::play_audio(&audio_handle, "./sounds/pop.mp3");
+                break;
+            }
+            if keymap::pressed(&keys, Action::Confirm) && chosen == 8 {
+                controls(profile, focused.clone());
+                break;
+            }
+            if (keymap::pressed(&keys, Action::Confirm) && chosen == rows.len() - 1)
+                || keymap::pressed(&keys, Action::Back)
+            {
+                let _ = profile.save();
+                return;
+            }
+        }
+    }
+}
+
+/// Rebinds the keys of the profile. Moves with the arrow keys whatever the keymap says,
+/// so a bad binding can always be undone here.
+pub fn controls(profile: &mut Profile, focused: Arc<Mutex<bool>>) {
+    let (screen_width, screen_height) = renderer::get_terminal_size();
+    let screen_width = screen_width as u16;
+    let screen_height = screen_height as u16;
+
+    let box_width: u16 = 50;
+    // every action, then reset and back
+    let row_count = Action::ALL.len() + 2;
+    let visible = row_count.min(screen_height.saturating_sub(6).max(3) as usize);
+
+    let (_stream, audio_handle) = OutputStream::try_default().unwrap();
+
+    let start_x = screen_width / 2 - box_width / 2;
+    let start_y = (screen_height / 2)
+        .saturating_sub(visible as u16 / 2 + 2)
+        .max(1);
+    let mut chosen: usize = 0;
+    let mut scroll = 0;
+    let mut waiting = false;
+
+    loop {
+        scroll = scroll.clamp(chosen.saturating_sub(visible - 1), chosen);
+
+        // print background image
+        out!("{esc}[H{esc}[48;2;0;0;0m", esc = 27 as char);
+        for _row in 0..=screen_height {
+            outln!("{}\r", " ".repeat(screen_width as usize),)
+        }
+
+        out!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        outln!(
+            "{esc}[{};{}H*{:-^3$}*",
+            start_y,
+            start_x,
+            " CONTROLS ",
+            (box_width - 2) as usize,
+            esc = 27 as char
+        );
+        outln!(
+            "{esc}[{};{}H|{: ^3$}|",
+            start_y + 1,
+            start_x,
+            if waiting {
+                "press the new key"
+            } else {
+                "enter to rebind, escape to go back"
+            },
+            (box_width - 2) as usize,
+            esc = 27 as char
+        );
+        for i in scroll..scroll + visible {
+            let row = match Action::ALL.get(i) {
+                Some(action) if waiting && i == chosen => {
+                    format!("{:<24}...", action.description())
+                }
+                Some(action) => format!("{:<24}{}", action.description(), keymap::label(*action)),
+                None if i == Action::ALL.len() => "Reset to defaults".to_string(),
+                None => "Back".to_string(),
+            };
+            if i == chosen {
+                out!("{esc}[48;2;46;46;46m", esc = 27 as char);
+            }
+            outln!(
+                "{esc}[{};{}H|{: ^3$}|",
+                start_y + 2 + (i - scroll) as u16,
+                start_x,
+                row,
+                (box_width - 2) as usize,
+                esc = 27 as char
+            );
+            out!("{esc}[48;2;0;0;0m", esc = 27 as char);
+        }
+        outln!(
+            "{esc}[{};{}H*{:-^3$}*",
+            start_y + 2 + visible as u16,
+            start_x,
+            "",
+            (box_width - 2) as usize,
+            esc = 27 as char
+        );
+
+        thread::sleep(Duration::from_millis(200));
+
+        if waiting {
+            // the enter that started the rebinding has to be let go first
+            while !input::menu_keys(*focused.lock().unwrap()).is_empty() {
+                thread::sleep(Duration::from_millis(10));
+            }
+            let key = loop {
+                if let Some(key) = input::menu_keys(*focused.lock().unwrap()).first() {
+                    break *key;
+                }
+                thread::sleep(Duration::from_millis(10));
+            };
+            profile.settings.keymap.bind(Action::ALL[chosen], key);
+            keymap::set_active(profile.settings.keymap);
+            audio::play_audio(&audio_handle, "./sounds/pop.mp3");
+            waiting = false;
+            continue;
+        }
+
+        //match input
+        loop {
+            let keys = input::menu_keys(*focused.lock().unwrap());
+
+            if keys.contains(&Keycode::Down) && chosen != row_count - 1 {
+                chosen += 1;
+                audio::play_audio(&audio_handle, "./sounds/pop.mp3");
+                break;
+            }
+            if keys.contains(&Keycode::Up) && chosen != 0 {
+                chosen -= 1;
+                audio::play_audio(&audio_handle, "./sounds/pop.mp3");
+                break;
+            }
+            if keys.contains(&Keycode::Enter) {
+                if chosen < Action::ALL.len() {
+                    waiting = true;
+                    break;
+                }
+                if chosen == Action::ALL.len() {
+                    profile.settings.keymap = Keymap::DEFAULT;
+                    keymap::set_active(profile.settings.keymap);
+                    audio::play_audio(&audio_handle, "./sounds/pop.mp3");
+                    break;
+                }
+                return;
+            }
+            if keys.contains(&Keycode::Escape) {
+                return;
+            }
+        }
+    }
+}
+
 fn exit_app() {
+    input::restore_terminal();
     let _ = crossterm::terminal::disable_raw_mode();
 
-    println!("\x1b[2J\x1b[H\x1b[48;2;0;0;0mGame closing\r");
+    outln!("\x1b[2J\x1b[H\x1b[48;2;0;0;0mGame closing\r");
     let _ = thread::spawn(|| {
         for x in stdin().bytes() {
             let _ = x;
         }
     });
     thread::sleep_ms(100);
-    println!("\x1b[2J\x1b[H\x1b[48;2;0;0;0mGame Closed\r");
+    outln!("\x1b[2J\x1b[H\x1b[48;2;0;0;0mGame Closed\r");
 
     std::process::exit(0);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_titles_are_cut_between_characters() {
+        let title = "Überlänge: ein sehr langer Titel mit Umlauten äöü und 🚀";
+        for width in 10..title.chars().count() {
+            let row = level_row(title, LevelState::Unlocked, false, width);
+            assert_eq!(row.chars().count(), width);
+            assert!(row.ends_with("..."));
+        }
+        let locked = level_row("Café", LevelState::Locked, true, 20);
+        assert_eq!(locked, "Café (locked)");
+        assert_eq!(
+            level_row("Café", LevelState::Completed(12.345), false, 8),
+            "Café ..."
+        );
+    }
+}
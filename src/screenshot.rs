@@ -0,0 +1,246 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::bvh::Bvh;
+use crate::camera::Camera;
+use crate::color::Palette;
+use crate::light::Light;
+use crate::loader;
+use crate::mat::{Tri, Vec3};
+use crate::pack;
+use crate::paths;
+use crate::renderer::{Backend, OutputMode, Screen};
+
+// the screenshot key renders this many times the pixels of the terminal each way
+pub const KEY_SCALE: usize = 2;
+const DEFAULT_SIZE: (usize, usize) = (640, 360);
+
+/// Renders the view of `camera` at `width` x `height` pixels, square ones unlike the
+/// cells of the terminal
+pub fn capture(
+    backend: Backend,
+    camera: &Camera,
+    level: &Bvh,
+    dynamic: &[Tri],
+    light: &Light,
+    width: usize,
+    height: usize,
+) -> Vec<Vec<Vec3>> {
+    // a half block cell is two pixels as wide as they are high
+    let screen = Screen::with_size(
+        width,
+        height.div_ceil(2),
+        OutputMode::HalfBlock,
+        Palette::TRUE_COLOR,
+    );
+    let mut image = backend
+        .renderer()
+        .render(&screen, camera, level, dynamic, light);
+    image.truncate(height);
+    image
+}
+
+/// Where the screenshot key saves to, named after the level and the time
+pub fn path(level_name: &str) -> PathBuf {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_millis());
+    paths::data_dir()
+        .join("screenshots")
+        .join(format!("{level_name}-{time}.png"))
+}
+
+/// Writes the image as PNG or PPM, whichever the extension of `path` says
+pub fn save(path: &Path, image: &[Vec<Vec3>]) -> Result<(), String> {
+    let bytes = match path.extension().and_then(|e| e.to_str()) {
+        Some("png") => png(image),
+        Some("ppm") => ppm(image),
+        _ => return Err(format!("{}: use a .png or .ppm file", path.display())),
+    };
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    }
+    fs::write(path, bytes).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn rgb(pixel: Vec3) -> [u8; 3] {
+    [pixel.x, pixel.y, pixel.z].map(|c| c.clamp(0., 255.) as u8)
+}
+
+fn ppm(image: &[Vec<Vec3>]) -> Vec<u8> {
+    let width = image.first().map_or(0, Vec::len);
+    let mut bytes = format!("P6\n{} {}\n255\n", width, image.len()).into_bytes();
+    for pixel in image.iter().flatten() {
+        bytes.extend(rgb(*pixel));
+    }
+    bytes
+}
+
+// uncompressed, the images are small and it keeps a deflate encoder out of the game
+fn png(image: &[Vec<Vec3>]) -> Vec<u8> {
+    let width = image.first().map_or(0, Vec::len);
+    let mut raw = Vec::with_capacity(image.len() * (width * 3 + 1));
+    for row in image {
+        // no filter
+        raw.push(0);
+        for pixel in row {
+            raw.extend(rgb(*pixel));
+        }
+    }
+
+    // zlib stream made of stored deflate blocks
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        zlib.push(blocks.peek().is_none() as u8);
+        zlib.extend(len.to_le_bytes());
+        zlib.extend((!len).to_le_bytes());
+        zlib.extend(block);
+    }
+    zlib.extend(adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend((width as u32).to_be_bytes());
+    header.extend((image.len() as u32).to_be_bytes());
+    // 8 bit RGB, deflate, no filter changes, not interlaced
+    header.extend([8, 2, 0, 0, 0]);
+
+    let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+    for (kind, data) in [(b"IHDR", header), (b"IDAT", zlib), (b"IEND", vec![])] {
+        bytes.extend((data.len() as u32).to_be_bytes());
+        let mut crc = crc32fast::Hasher::new();
+        crc.update(kind);
+        crc.update(&data);
+        bytes.extend(kind);
+        bytes.extend(data);
+        bytes.extend(crc.finalize().to_be_bytes());
+    }
+    bytes
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+/// `termtrack screenshot <level_file> <image> [width height]`, saves the view from the
+/// start of a level, for thumbnails. Returns the exit code.
+pub fn run(args: &[String]) -> i32 {
+    let usage = "usage: termtrack screenshot <level_file> <image.png|image.ppm> [width height]";
+    let (Some(level), Some(image_path)) = (args.first(), args.get(1)) else {
+        eprintln!("{usage}");
+        return 2;
+    };
+    let (width, height) = match &args[2..] {
+        [] => DEFAULT_SIZE,
+        [w, h] => match (w.parse::<usize>(), h.parse::<usize>()) {
+            (Ok(w), Ok(h)) if w > 0 && h > 0 => (w, h),
+            _ => {
+                eprintln!("width and height must be positive numbers");
+                return 2;
+            }
+        },
+        _ => {
+            eprintln!("{usage}");
+            return 2;
+        }
+    };
+    let level_path = PathBuf::from(level);
+    let map = match loader::load(&level_path) {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("{level}: {e}");
+            return 1;
+        }
+    };
+    let camera = Camera {
+        pos: Vec3 {
+            x: map.start_pos.0,
+            y: map.start_pos.1,
+            z: map.start_pos.2,
+        },
+        ..Default::default()
+    };
+    // lit like in the game if the folder of the level has a manifest
+    let light = level_path
+        .parent()
+        .and_then(|dir| pack::load(dir).ok())
+        .and_then(|pack| pack.levels.into_iter().find(|l| l.path == level_path))
+        .map_or(map.light, |l| l.light);
+    let light = Light {
+        shadows: true,
+        ..light
+    };
+    let image = capture(
+        Backend::Raster,
+        &camera,
+        &map.bvh,
+        &[],
+        &light,
+        width,
+        height,
+    );
+    match save(Path::new(image_path), &image) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{e}");
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image() -> Vec<Vec<Vec3>> {
+        let pixel = |x: f64| Vec3 { x, y: 20., z: 300. };
+        vec![vec![pixel(0.), pixel(10.)], vec![pixel(255.), pixel(-5.)]]
+    }
+
+    #[test]
+    fn writes_ppm() {
+        let bytes = ppm(&image());
+        assert!(bytes.starts_with(b"P6\n2 2\n255\n"));
+        assert_eq!(
+            &bytes[11..],
+            &[0, 20, 255, 10, 20, 255, 255, 20, 255, 0, 20, 255]
+        );
+    }
+
+    #[test]
+    fn writes_png_chunks() {
+        let bytes = png(&image());
+        assert!(bytes.starts_with(b"\x89PNG\r\n\x1a\n"));
+        // IHDR right after the signature, 2x2
+        assert_eq!(&bytes[12..16], b"IHDR");
+        assert_eq!(&bytes[16..24], &[0, 0, 0, 2, 0, 0, 0, 2]);
+        assert!(bytes.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+    }
+
+    #[test]
+    fn captures_at_any_size() {
+        let map = loader::load(&PathBuf::from("level_pack_0/level_0.txt")).unwrap();
+        let camera = Camera {
+            pos: Vec3 {
+                x: map.start_pos.0,
+                y: map.start_pos.1,
+                z: map.start_pos.2,
+            },
+            ..Default::default()
+        };
+        let image = capture(Backend::Raster, &camera, &map.bvh, &[], &map.light, 64, 37);
+        assert_eq!(image.len(), 37);
+        assert!(image.iter().all(|row| row.len() == 64));
+    }
+}
@@ -0,0 +1,96 @@
+/// Pattern drawn over the colour of a tri. Procedural, so it costs no memory and stays
+/// sharp at any distance the terminal resolves.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Texture {
+    #[default]
+    Flat,
+    // squares of two shades, for floors
+    Checker,
+    // rows of bricks with mortar between them, for walls
+    Brick,
+}
+
+// sizes in world units, dividing a block (10 wide, 15 high) so tiles line up
+const CHECKER_SIZE: f64 = 5.;
+const BRICK_WIDTH: f64 = 5.;
+const BRICK_HEIGHT: f64 = 2.5;
+// thick enough not to flicker between cells at a distance
+const MORTAR: f64 = 0.5;
+
+impl Texture {
+    /// How bright the texture is at `(u, v)` in world units, 1 is the tri colour as it is
+    pub fn sample(self, u: f64, v: f64) -> f64 {
+        match self {
+            Texture::Flat => 1.,
+            Texture::Checker => {
+                let square = (u / CHECKER_SIZE).floor() + (v / CHECKER_SIZE).floor();
+                if square.rem_euclid(2.) == 0. {
+                    1.
+                } else {
+                    0.8
+                }
+            }
+            Texture::Brick => {
+                let row = (v / BRICK_HEIGHT).floor();
+                // every other row is moved half a brick along
+                let offset = row.rem_euclid(2.) * BRICK_WIDTH / 2.;
+                let x = (u + offset).rem_euclid(BRICK_WIDTH);
+                let y = v.rem_euclid(BRICK_HEIGHT);
+                if x < MORTAR || y < MORTAR {
+                    0.65
+                } else {
+                    1.
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mat::{Mesh, Vec3};
+
+    #[test]
+    fn checker_alternates() {
+        let checker = Texture::Checker;
+        assert_eq!(checker.sample(1., 1.), checker.sample(6., 6.));
+        assert_ne!(checker.sample(1., 1.), checker.sample(6., 1.));
+        // no double width square around 0
+        assert_ne!(checker.sample(-1., 1.), checker.sample(1., 1.));
+        assert_eq!(Texture::Flat.sample(6., 1.), 1.);
+    }
+
+    #[test]
+    fn bricks_are_staggered() {
+        let brick = Texture::Brick;
+        // mortar between rows, and between bricks half a brick apart in the next row
+        assert!(brick.sample(2., 0.2) < 1.);
+        assert_eq!(brick.sample(2., 1.5), 1.);
+        assert!(brick.sample(0.2, 1.5) < 1.);
+        assert_eq!(brick.sample(0.2, 4.), 1.);
+        assert!(brick.sample(2.6, 4.) < 1.);
+    }
+
+    #[test]
+    fn tris_are_textured_where_hit() {
+        // a floor square split in two, seen from above
+        let floor = Mesh::new(vec![
+            (0., 0., 0.),
+            (10., 0., 0.),
+            (10., 0., 10.),
+            (100., 100., 100.),
+            (0., 0., 0.),
+            (0., 0., 10.),
+            (10., 0., 10.),
+            (100., 100., 100.),
+        ])
+        .with_texture(Texture::Checker);
+        let at = |tri: usize, x: f64, z: f64| floor.tris[tri].color_at(Vec3 { x, y: 0., z }).x;
+        assert_eq!(at(0, 4., 1.), 100.);
+        assert_eq!(at(0, 9., 1.), 80.);
+        // the squares carry on across the edge between the two tris
+        assert_eq!(at(1, 1., 9.), 80.);
+        assert_eq!(at(1, 1., 4.), at(0, 4., 1.));
+    }
+}